@@ -1,5 +1,6 @@
 use criterion::{criterion_group, criterion_main, Criterion};
 use lazy_tmux::path::tmux_dirs;
+use lazy_tmux::plugins::ConfigFile;
 use lazy_tmux::{install_plugins, install_plugins_one_by_one};
 use std::fs;
 use std::sync::Once;
@@ -14,20 +15,27 @@ fn setup_plugins() {
     });
 }
 
+fn load_config() -> ConfigFile {
+    let path = ConfigFile::path(None).unwrap();
+    ConfigFile::load(&path).unwrap_or_default()
+}
+
 fn bench_parallel(c: &mut Criterion) {
     setup_plugins();
+    let config = load_config();
     c.bench_function("install_plugins (parallel)", |b| {
         b.iter(|| {
-            install_plugins().unwrap();
+            install_plugins(&config);
         });
     });
 }
 
 fn bench_sequential(c: &mut Criterion) {
     setup_plugins();
+    let config = load_config();
     c.bench_function("install_plugins_one_by_one (sequential)", |b| {
         b.iter(|| {
-            install_plugins_one_by_one().unwrap();
+            install_plugins_one_by_one(&config);
         });
     });
 }
@@ -1,6 +1,6 @@
 # Print an optspec for argparse to handle cmd's options that are independent of any subcommand.
 function __fish_roxide_global_optspecs
-	string join \n c/config= h/help V/version
+	string join \n c/config= j/jobs= h/help V/version
 end
 
 function __fish_roxide_needs_command
@@ -24,20 +24,75 @@ function __fish_roxide_using_subcommand
 	contains -- $cmd[1] $argv
 end
 
-complete -c roxide -n "__fish_roxide_needs_command" -s c -l config -d 'Sets a custom config file' -r -F
+complete -c roxide -n "__fish_roxide_needs_command" -s c -l config -d 'Sets a custom config file (falls back to `$LAZY_TMUX_CONFIG`)' -r -F
+complete -c roxide -n "__fish_roxide_needs_command" -s j -l jobs -d 'Runs at most N git operations at once (overrides `[settings] jobs`)' -r
 complete -c roxide -n "__fish_roxide_needs_command" -s h -l help -d 'Print help'
 complete -c roxide -n "__fish_roxide_needs_command" -s V -l version -d 'Print version'
 complete -c roxide -n "__fish_roxide_needs_command" -f -a "install" -d 'Installs plugins listed in config file `$CONFIG_HOME/tmux/plugins.toml`'
 complete -c roxide -n "__fish_roxide_needs_command" -f -a "update" -d 'Updates plugins listed in config file `$CONFIG_HOME/tmux/plugins.toml`'
 complete -c roxide -n "__fish_roxide_needs_command" -f -a "clean" -d 'Uninstalls plugins not listed in config file `$CONFIG_HOME/tmux/plugins.toml`'
+complete -c roxide -n "__fish_roxide_needs_command" -f -a "add" -d 'Adds a plugin to the config file'
+complete -c roxide -n "__fish_roxide_needs_command" -f -a "remove" -d 'Removes a plugin from the config file'
+complete -c roxide -n "__fish_roxide_needs_command" -f -a "build" -d 'Reruns a plugin\'s `build` hook'
 complete -c roxide -n "__fish_roxide_needs_command" -f -a "init" -d 'Sources plugins listed in config file `$CONFIG_HOME/tmux/plugins.toml`'
+complete -c roxide -n "__fish_roxide_needs_command" -f -a "load" -d 'Sources a single plugin once per tmux server, run by the keys and hooks of lazy plugins'
+complete -c roxide -n "__fish_roxide_needs_command" -f -a "restore" -d 'Resets installed plugins to the commits recorded in `plugins.lock`'
+complete -c roxide -n "__fish_roxide_needs_command" -f -a "outdated" -d 'Checks plugins for upstream changes without applying them, exits 1 if any are behind'
+complete -c roxide -n "__fish_roxide_needs_command" -f -a "list" -d 'Lists plugins listed in config file `$CONFIG_HOME/tmux/plugins.toml` and their state on disk'
 complete -c roxide -n "__fish_roxide_needs_command" -f -a "help" -d 'Print this message or the help of the given subcommand(s)'
+complete -c roxide -n "__fish_roxide_using_subcommand install" -s c -l config -d 'Sets a custom config file (falls back to `$LAZY_TMUX_CONFIG`)' -r -F
+complete -c roxide -n "__fish_roxide_using_subcommand install" -s j -l jobs -d 'Runs at most N git operations at once (overrides `[settings] jobs`)' -r
+complete -c roxide -n "__fish_roxide_using_subcommand install" -l locked -d 'Check out exactly the commits recorded in `plugins.lock`'
 complete -c roxide -n "__fish_roxide_using_subcommand install" -s h -l help -d 'Print help'
+complete -c roxide -n "__fish_roxide_using_subcommand update" -s c -l config -d 'Sets a custom config file (falls back to `$LAZY_TMUX_CONFIG`)' -r -F
+complete -c roxide -n "__fish_roxide_using_subcommand update" -s j -l jobs -d 'Runs at most N git operations at once (overrides `[settings] jobs`)' -r
+complete -c roxide -n "__fish_roxide_using_subcommand update" -l json -d 'Print the per plugin report as JSON'
 complete -c roxide -n "__fish_roxide_using_subcommand update" -s h -l help -d 'Print help'
+complete -c roxide -n "__fish_roxide_using_subcommand clean" -s c -l config -d 'Sets a custom config file (falls back to `$LAZY_TMUX_CONFIG`)' -r -F
+complete -c roxide -n "__fish_roxide_using_subcommand clean" -s j -l jobs -d 'Runs at most N git operations at once (overrides `[settings] jobs`)' -r
+complete -c roxide -n "__fish_roxide_using_subcommand clean" -l dry-run -d 'Only list the plugins that would be removed'
+complete -c roxide -n "__fish_roxide_using_subcommand clean" -s y -l yes -d 'Remove without asking for confirmation'
 complete -c roxide -n "__fish_roxide_using_subcommand clean" -s h -l help -d 'Print help'
+complete -c roxide -n "__fish_roxide_using_subcommand add" -l branch -d 'Branch to follow instead of the remote\'s default' -r
+complete -c roxide -n "__fish_roxide_using_subcommand add" -l host -d 'Git host, `github.com` when not given' -r
+complete -c roxide -n "__fish_roxide_using_subcommand add" -s c -l config -d 'Sets a custom config file (falls back to `$LAZY_TMUX_CONFIG`)' -r -F
+complete -c roxide -n "__fish_roxide_using_subcommand add" -s j -l jobs -d 'Runs at most N git operations at once (overrides `[settings] jobs`)' -r
+complete -c roxide -n "__fish_roxide_using_subcommand add" -l install -d 'Install the plugin right away'
+complete -c roxide -n "__fish_roxide_using_subcommand add" -s h -l help -d 'Print help'
+complete -c roxide -n "__fish_roxide_using_subcommand remove" -s c -l config -d 'Sets a custom config file (falls back to `$LAZY_TMUX_CONFIG`)' -r -F
+complete -c roxide -n "__fish_roxide_using_subcommand remove" -s j -l jobs -d 'Runs at most N git operations at once (overrides `[settings] jobs`)' -r
+complete -c roxide -n "__fish_roxide_using_subcommand remove" -l clean -d 'Uninstall the plugin right away'
+complete -c roxide -n "__fish_roxide_using_subcommand remove" -s h -l help -d 'Print help'
+complete -c roxide -n "__fish_roxide_using_subcommand build" -s c -l config -d 'Sets a custom config file (falls back to `$LAZY_TMUX_CONFIG`)' -r -F
+complete -c roxide -n "__fish_roxide_using_subcommand build" -s j -l jobs -d 'Runs at most N git operations at once (overrides `[settings] jobs`)' -r
+complete -c roxide -n "__fish_roxide_using_subcommand build" -s h -l help -d 'Print help'
+complete -c roxide -n "__fish_roxide_using_subcommand init" -s c -l config -d 'Sets a custom config file (falls back to `$LAZY_TMUX_CONFIG`)' -r -F
+complete -c roxide -n "__fish_roxide_using_subcommand init" -s j -l jobs -d 'Runs at most N git operations at once (overrides `[settings] jobs`)' -r
+complete -c roxide -n "__fish_roxide_using_subcommand init" -l background -d 'Install missing plugins and source them, run detached by `init` itself'
 complete -c roxide -n "__fish_roxide_using_subcommand init" -s h -l help -d 'Print help'
-complete -c roxide -n "__fish_roxide_using_subcommand help; and not __fish_seen_subcommand_from install update clean init help" -f -a "install" -d 'Installs plugins listed in config file `$CONFIG_HOME/tmux/plugins.toml`'
-complete -c roxide -n "__fish_roxide_using_subcommand help; and not __fish_seen_subcommand_from install update clean init help" -f -a "update" -d 'Updates plugins listed in config file `$CONFIG_HOME/tmux/plugins.toml`'
-complete -c roxide -n "__fish_roxide_using_subcommand help; and not __fish_seen_subcommand_from install update clean init help" -f -a "clean" -d 'Uninstalls plugins not listed in config file `$CONFIG_HOME/tmux/plugins.toml`'
-complete -c roxide -n "__fish_roxide_using_subcommand help; and not __fish_seen_subcommand_from install update clean init help" -f -a "init" -d 'Sources plugins listed in config file `$CONFIG_HOME/tmux/plugins.toml`'
-complete -c roxide -n "__fish_roxide_using_subcommand help; and not __fish_seen_subcommand_from install update clean init help" -f -a "help" -d 'Print this message or the help of the given subcommand(s)'
+complete -c roxide -n "__fish_roxide_using_subcommand load" -l key -d 'Replays the `[table] key` that triggered the load once it\'s sourced' -r
+complete -c roxide -n "__fish_roxide_using_subcommand load" -s c -l config -d 'Sets a custom config file (falls back to `$LAZY_TMUX_CONFIG`)' -r -F
+complete -c roxide -n "__fish_roxide_using_subcommand load" -s j -l jobs -d 'Runs at most N git operations at once (overrides `[settings] jobs`)' -r
+complete -c roxide -n "__fish_roxide_using_subcommand load" -s h -l help -d 'Print help'
+complete -c roxide -n "__fish_roxide_using_subcommand restore" -s c -l config -d 'Sets a custom config file (falls back to `$LAZY_TMUX_CONFIG`)' -r -F
+complete -c roxide -n "__fish_roxide_using_subcommand restore" -s j -l jobs -d 'Runs at most N git operations at once (overrides `[settings] jobs`)' -r
+complete -c roxide -n "__fish_roxide_using_subcommand restore" -s h -l help -d 'Print help'
+complete -c roxide -n "__fish_roxide_using_subcommand outdated" -s c -l config -d 'Sets a custom config file (falls back to `$LAZY_TMUX_CONFIG`)' -r -F
+complete -c roxide -n "__fish_roxide_using_subcommand outdated" -s j -l jobs -d 'Runs at most N git operations at once (overrides `[settings] jobs`)' -r
+complete -c roxide -n "__fish_roxide_using_subcommand outdated" -s h -l help -d 'Print help'
+complete -c roxide -n "__fish_roxide_using_subcommand list" -s c -l config -d 'Sets a custom config file (falls back to `$LAZY_TMUX_CONFIG`)' -r -F
+complete -c roxide -n "__fish_roxide_using_subcommand list" -s j -l jobs -d 'Runs at most N git operations at once (overrides `[settings] jobs`)' -r
+complete -c roxide -n "__fish_roxide_using_subcommand list" -l json -d 'Print the list as JSON'
+complete -c roxide -n "__fish_roxide_using_subcommand list" -s h -l help -d 'Print help'
+complete -c roxide -n "__fish_roxide_using_subcommand help; and not __fish_seen_subcommand_from install update clean add remove build init load restore outdated list help" -f -a "install" -d 'Installs plugins listed in config file `$CONFIG_HOME/tmux/plugins.toml`'
+complete -c roxide -n "__fish_roxide_using_subcommand help; and not __fish_seen_subcommand_from install update clean add remove build init load restore outdated list help" -f -a "update" -d 'Updates plugins listed in config file `$CONFIG_HOME/tmux/plugins.toml`'
+complete -c roxide -n "__fish_roxide_using_subcommand help; and not __fish_seen_subcommand_from install update clean add remove build init load restore outdated list help" -f -a "clean" -d 'Uninstalls plugins not listed in config file `$CONFIG_HOME/tmux/plugins.toml`'
+complete -c roxide -n "__fish_roxide_using_subcommand help; and not __fish_seen_subcommand_from install update clean add remove build init load restore outdated list help" -f -a "add" -d 'Adds a plugin to the config file'
+complete -c roxide -n "__fish_roxide_using_subcommand help; and not __fish_seen_subcommand_from install update clean add remove build init load restore outdated list help" -f -a "remove" -d 'Removes a plugin from the config file'
+complete -c roxide -n "__fish_roxide_using_subcommand help; and not __fish_seen_subcommand_from install update clean add remove build init load restore outdated list help" -f -a "build" -d 'Reruns a plugin\'s `build` hook'
+complete -c roxide -n "__fish_roxide_using_subcommand help; and not __fish_seen_subcommand_from install update clean add remove build init load restore outdated list help" -f -a "init" -d 'Sources plugins listed in config file `$CONFIG_HOME/tmux/plugins.toml`'
+complete -c roxide -n "__fish_roxide_using_subcommand help; and not __fish_seen_subcommand_from install update clean add remove build init load restore outdated list help" -f -a "load" -d 'Sources a single plugin once per tmux server, run by the keys and hooks of lazy plugins'
+complete -c roxide -n "__fish_roxide_using_subcommand help; and not __fish_seen_subcommand_from install update clean add remove build init load restore outdated list help" -f -a "restore" -d 'Resets installed plugins to the commits recorded in `plugins.lock`'
+complete -c roxide -n "__fish_roxide_using_subcommand help; and not __fish_seen_subcommand_from install update clean add remove build init load restore outdated list help" -f -a "outdated" -d 'Checks plugins for upstream changes without applying them, exits 1 if any are behind'
+complete -c roxide -n "__fish_roxide_using_subcommand help; and not __fish_seen_subcommand_from install update clean add remove build init load restore outdated list help" -f -a "list" -d 'Lists plugins listed in config file `$CONFIG_HOME/tmux/plugins.toml` and their state on disk'
+complete -c roxide -n "__fish_roxide_using_subcommand help; and not __fish_seen_subcommand_from install update clean add remove build init load restore outdated list help" -f -a "help" -d 'Print this message or the help of the given subcommand(s)'
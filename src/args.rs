@@ -1,5 +1,6 @@
 
 /// .
+use std::num::NonZeroUsize;
 use std::path::PathBuf;
 
 use clap::{Parser, Subcommand};
@@ -8,10 +9,14 @@ use clap::{Parser, Subcommand};
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 pub struct Cli {
-    /// Sets a custom config file
-    #[arg(short, long, value_name = "FILE")]
+    /// Sets a custom config file (falls back to `$LAZY_TMUX_CONFIG`)
+    #[arg(short, long, value_name = "FILE", global = true)]
     pub config: Option<PathBuf>,
 
+    /// Runs at most N git operations at once (overrides `[settings] jobs`)
+    #[arg(short, long, value_name = "N", global = true)]
+    pub jobs: Option<NonZeroUsize>,
+
     #[allow(missing_docs)]
     #[command(subcommand)]
     pub command: Option<Commands>,
@@ -21,11 +26,75 @@ pub struct Cli {
 #[derive(Subcommand)]
 pub enum Commands {
     /// Installs plugins listed in config file `$CONFIG_HOME/tmux/plugins.toml`
-    Install,
+    Install {
+        /// Check out exactly the commits recorded in `plugins.lock`
+        #[arg(long)]
+        locked: bool,
+    },
     /// Updates plugins listed in config file `$CONFIG_HOME/tmux/plugins.toml`
-    Update,
+    Update {
+        /// Print the per plugin report as JSON
+        #[arg(long)]
+        json: bool,
+    },
     /// Uninstalls plugins not listed in config file `$CONFIG_HOME/tmux/plugins.toml`
-    Clean,
+    Clean {
+        /// Only list the plugins that would be removed
+        #[arg(long)]
+        dry_run: bool,
+        /// Remove without asking for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Adds a plugin to the config file
+    Add {
+        /// The plugin as `[host/]owner/repo[@branch|@tag:name]`
+        plugin: String,
+        /// Branch to follow instead of the remote's default
+        #[arg(long)]
+        branch: Option<String>,
+        /// Git host, `github.com` when not given
+        #[arg(long)]
+        host: Option<String>,
+        /// Install the plugin right away
+        #[arg(long)]
+        install: bool,
+    },
+    /// Removes a plugin from the config file
+    Remove {
+        /// The plugin as `owner/repo`
+        plugin: String,
+        /// Uninstall the plugin right away
+        #[arg(long)]
+        clean: bool,
+    },
+    /// Reruns a plugin's `build` hook
+    Build {
+        /// The plugin as `owner/repo`
+        plugin: String,
+    },
     /// Sources plugins listed in config file `$CONFIG_HOME/tmux/plugins.toml`
-    Init,
+    Init {
+        /// Install missing plugins and source them, run detached by `init` itself
+        #[arg(long, hide = true)]
+        background: bool,
+    },
+    /// Sources a single plugin once per tmux server, run by the keys and hooks of lazy plugins
+    Load {
+        /// The plugin as `owner/repo`
+        plugin: String,
+        /// Replays the `[table] key` that triggered the load once it's sourced
+        #[arg(long)]
+        key: Option<String>,
+    },
+    /// Resets installed plugins to the commits recorded in `plugins.lock`
+    Restore,
+    /// Checks plugins for upstream changes without applying them, exits 1 if any are behind
+    Outdated,
+    /// Lists plugins listed in config file `$CONFIG_HOME/tmux/plugins.toml` and their state on disk
+    List {
+        /// Print the list as JSON
+        #[arg(long)]
+        json: bool,
+    },
 }
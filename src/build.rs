@@ -0,0 +1,65 @@
+use std::path::Path;
+use std::process::Command;
+
+use crate::error::BuildError;
+
+/// Runs `commands` with `sh -c` inside `dir`, one after another, stopping at
+/// the first that fails, and returns their combined output.
+///
+/// # Errors
+/// Fails if a command can't be started or exits non-zero, the error carries
+/// everything it printed.
+pub fn run(commands: &[impl AsRef<str>], dir: &Path) -> Result<String, BuildError> {
+    let mut log = String::new();
+    for command in commands {
+        let command = command.as_ref();
+        let out = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .current_dir(dir)
+            .output()
+            .map_err(|source| BuildError::Spawn {
+                command: command.to_string(),
+                source,
+            })?;
+        let output = format!(
+            "{}{}",
+            String::from_utf8_lossy(&out.stdout),
+            String::from_utf8_lossy(&out.stderr)
+        );
+        if !out.status.success() {
+            return Err(BuildError::Failed {
+                command: command.to_string(),
+                status: out.status,
+                output,
+            });
+        }
+        log.push_str(&output);
+    }
+    Ok(log)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stops_at_first_failure() {
+        let dir = std::env::temp_dir();
+        assert_eq!(
+            run(&["echo one", "echo two >&2"], &dir).unwrap(),
+            "one\ntwo\n"
+        );
+
+        let err = run(&["echo building", "exit 3", "echo never"], &dir).unwrap_err();
+        let BuildError::Failed {
+            command, output, ..
+        } = &err
+        else {
+            panic!("expected a failed build");
+        };
+        assert_eq!((command.as_str(), output.as_str()), ("exit 3", ""));
+        assert_eq!(err.to_string(), "build `exit 3` failed (exit status: 3)");
+    }
+}
@@ -0,0 +1,208 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use walkdir::WalkDir;
+
+use crate::plugins::{ConfigFile, Plugins};
+
+/// An installed `owner/repo` directory that is no longer listed in the config file.
+#[derive(Debug, PartialEq)]
+pub struct Orphan {
+    owner: String,
+    repo: String,
+    path: PathBuf,
+    size: u64,
+}
+
+impl Orphan {
+    /// getter method for owner
+    #[inline]
+    pub fn owner(&self) -> &str {
+        &self.owner
+    }
+
+    /// getter method for repo
+    #[inline]
+    pub fn repo(&self) -> &str {
+        &self.repo
+    }
+
+    /// getter method for path
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// disk usage of the plugin in bytes
+    #[inline]
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+/// Walks `root` (the plugin root used by `PluginDir`) and returns every
+/// `owner/repo` clone that has no matching entry in `config`, local
+/// plugins that happen to live under `root` are never orphans.
+///
+/// Only directories with a `.git` count, `root` may also hold what other
+/// managers installed there, e.g. TPM's `tpm/bin`, which is left alone.
+///
+/// # Errors
+/// Fails if `root` or one of its owner directories can't be read.
+pub fn find_orphans(config: &ConfigFile, root: &Path) -> io::Result<Vec<Orphan>> {
+    // skipped plugins may be wanted again on another machine or shell
+    let wanted: HashSet<(&str, &str)> = config
+        .plugins()
+        .iter()
+        .chain(config.skipped())
+        .map(|p| (p.owner(), p.repo()))
+        .collect();
+    let local: HashSet<PathBuf> = config
+        .plugins()
+        .iter()
+        .filter_map(Plugins::local_path)
+        .collect();
+
+    let mut orphans = Vec::new();
+    if !root.exists() {
+        return Ok(orphans);
+    }
+    for owner in sorted_dirs(root)? {
+        let owner_name = file_name(&owner);
+        for repo in sorted_dirs(&owner)? {
+            let repo_name = file_name(&repo);
+            if !repo.join(".git").exists() {
+                continue;
+            }
+            if !wanted.contains(&(owner_name.as_str(), repo_name.as_str()))
+                && !local.contains(&repo)
+            {
+                orphans.push(Orphan {
+                    owner: owner_name.clone(),
+                    repo: repo_name,
+                    size: disk_usage(&repo),
+                    path: repo,
+                });
+            }
+        }
+    }
+    Ok(orphans)
+}
+
+/// Removes the given orphans, then every owner directory under `root` left empty.
+///
+/// # Errors
+/// Fails on the first directory that can't be removed.
+pub fn remove_orphans(orphans: &[Orphan], root: &Path) -> io::Result<()> {
+    for orphan in orphans {
+        fs::remove_dir_all(&orphan.path)?;
+    }
+    if !root.exists() {
+        return Ok(());
+    }
+    for owner in sorted_dirs(root)? {
+        if fs::read_dir(&owner)?.next().is_none() {
+            fs::remove_dir(owner)?;
+        }
+    }
+    Ok(())
+}
+
+/// Formats a byte count the way `du -h` does, e.g. `1.5M`.
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "K", "M", "G", "T"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{}{}", bytes, UNITS[0])
+    } else {
+        format!("{:.1}{}", size, UNITS[unit])
+    }
+}
+
+fn sorted_dirs(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut dirs = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let hidden = entry.file_name().to_string_lossy().starts_with('.');
+        if entry.file_type()?.is_dir() && !hidden {
+            dirs.push(entry.path());
+        }
+    }
+    dirs.sort();
+    Ok(dirs)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn disk_usage(path: &Path) -> u64 {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter_map(|e| e.metadata().ok())
+        .filter(|m| m.is_file())
+        .map(|m| m.len())
+        .sum()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_and_removes_orphans() {
+        let root = std::env::temp_dir().join(format!("lazy-tmux-clean-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("tmux-plugins/tmux-sensible/.git")).unwrap();
+        fs::create_dir_all(root.join("abhinandh-s/lazy.tmux/.git")).unwrap();
+        fs::create_dir_all(root.join("empty-owner")).unwrap();
+        // a TPM install in the same dir
+        fs::create_dir_all(root.join("tpm/.git")).unwrap();
+        fs::create_dir_all(root.join("tpm/bin")).unwrap();
+        fs::create_dir_all(root.join("tmux-resurrect/scripts")).unwrap();
+        fs::write(
+            root.join("tmux-plugins/tmux-sensible/sensible.tmux"),
+            "abcd",
+        )
+        .unwrap();
+
+        let config = ConfigFile::parse(
+            "[[plugins]]\nowner = \"abhinandh-s\"\nrepo = \"lazy.tmux\"\n",
+            Path::new("plugins.toml"),
+        )
+        .unwrap();
+        let orphans = find_orphans(&config, &root).unwrap();
+        assert_eq!(orphans.len(), 1);
+        assert_eq!(
+            (orphans[0].owner(), orphans[0].repo()),
+            ("tmux-plugins", "tmux-sensible")
+        );
+        assert_eq!(orphans[0].size(), 4);
+
+        remove_orphans(&orphans, &root).unwrap();
+        assert!(!root.join("tmux-plugins").exists());
+        assert!(!root.join("empty-owner").exists());
+        assert!(root.join("abhinandh-s/lazy.tmux").exists());
+        assert!(root.join("tpm/bin").exists());
+        assert!(root.join("tmux-resurrect/scripts").exists());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn human_sizes() {
+        assert_eq!(human_size(512), "512B");
+        assert_eq!(human_size(1536), "1.5K");
+        assert_eq!(human_size(3 * 1024 * 1024), "3.0M");
+    }
+}
@@ -0,0 +1,317 @@
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+use toml_edit::{Array, ArrayOfTables, DocumentMut, InlineTable, Item, Table, Value};
+
+use crate::error::EditError;
+use crate::plugins::{ConfigFile, Plugins};
+use crate::spec::PluginSpec;
+
+/// `plugins.toml` opened for editing, comments, whitespace and ordering
+/// are kept as they are.
+#[derive(Debug)]
+pub struct ConfigEdit {
+    doc: DocumentMut,
+    path: PathBuf,
+}
+
+impl ConfigEdit {
+    /// reads the config file at `path`, a missing file is treated as empty
+    ///
+    /// # Errors
+    /// Fails if the file exists but can't be read or isn't valid TOML.
+    pub fn open(path: &Path) -> Result<Self, EditError> {
+        let input = match std::fs::read_to_string(path) {
+            Ok(input) => input,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(source) => {
+                return Err(EditError::Read {
+                    path: path.to_path_buf(),
+                    source,
+                });
+            }
+        };
+        Self::parse(&input, path)
+    }
+
+    /// parses `input` as the config file at `path`
+    ///
+    /// # Errors
+    /// Fails if `input` isn't valid TOML.
+    pub fn parse(input: &str, path: &Path) -> Result<Self, EditError> {
+        let doc = input.parse().map_err(|source| EditError::Toml {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Ok(Self {
+            doc,
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// appends a plugin entry, in the same style (`[[plugins]]` tables, inline
+    /// tables or spec strings) the file already uses
+    ///
+    /// # Errors
+    /// Fails if a plugin with the same owner and repo is already listed.
+    pub fn add(&mut self, spec: &PluginSpec) -> Result<(), EditError> {
+        if self.position(spec.owner(), spec.repo())?.is_some() {
+            return Err(EditError::Duplicate(format!(
+                "{}/{}",
+                spec.owner(),
+                spec.repo()
+            )));
+        }
+        let mut fields = vec![("owner", spec.owner()), ("repo", spec.repo())];
+        if let Some(host) = spec.host() {
+            fields.push(("platform", host));
+        }
+        if let Some(branch) = spec.branch() {
+            fields.push(("branch", branch));
+        }
+        if let Some(tag) = spec.tag() {
+            fields.push(("tag", tag));
+        }
+
+        let path = self.path.clone();
+        let entry = self
+            .doc
+            .entry("plugins")
+            .or_insert_with(|| Item::ArrayOfTables(ArrayOfTables::new()));
+        match entry {
+            Item::ArrayOfTables(tables) => {
+                let mut table = Table::new();
+                for (key, value) in fields {
+                    table.insert(key, toml_edit::value(value));
+                }
+                tables.push(table);
+            }
+            Item::Value(Value::Array(array)) => {
+                let strings = array.iter().last().is_some_and(Value::is_str);
+                let short = spec.to_string();
+                if strings && short.parse().as_ref() == Ok(spec) {
+                    push_inline(array, short.into());
+                } else {
+                    let mut table = InlineTable::new();
+                    for (key, value) in fields {
+                        table.insert(key, value.into());
+                    }
+                    push_inline(array, table.into());
+                }
+            }
+            _ => return Err(EditError::Malformed(path)),
+        }
+        Ok(())
+    }
+
+    /// removes the entry for `owner/repo`
+    ///
+    /// # Errors
+    /// Fails if no such plugin is listed.
+    pub fn remove(&mut self, owner: &str, repo: &str) -> Result<(), EditError> {
+        let Some(index) = self.position(owner, repo)? else {
+            return Err(EditError::NotFound(format!("{}/{}", owner, repo)));
+        };
+        match self.doc.get_mut("plugins") {
+            Some(Item::ArrayOfTables(tables)) => {
+                let prefix = tables
+                    .get(index)
+                    .and_then(|t| t.decor().prefix())
+                    .and_then(|p| p.as_str())
+                    .map(str::to_string);
+                tables.remove(index);
+                // the next table takes the removed one's place, don't leave
+                // a blank line where it started unless the next one has comments
+                if let Some(next) = tables.get_mut(index)
+                    && let Some(prefix) = prefix
+                    && next
+                        .decor()
+                        .prefix()
+                        .and_then(|p| p.as_str())
+                        .is_some_and(|p| p.trim().is_empty())
+                {
+                    next.decor_mut().set_prefix(prefix);
+                }
+            }
+            Some(Item::Value(Value::Array(array))) => {
+                let removed = array.remove(index);
+                if let Some(next) = array.get_mut(index) {
+                    let prefix = removed.decor().prefix().cloned().unwrap_or_default();
+                    next.decor_mut().set_prefix(prefix);
+                }
+            }
+            _ => return Err(EditError::Malformed(self.path.clone())),
+        }
+        Ok(())
+    }
+
+    /// checks the edited config is still valid and writes it back
+    ///
+    /// # Errors
+    /// Fails if the result isn't a valid config or can't be written.
+    pub fn save(&self) -> Result<(), EditError> {
+        let output = self.doc.to_string();
+        ConfigFile::parse(&output, &self.path)?;
+        std::fs::write(&self.path, output).map_err(|source| EditError::Write {
+            path: self.path.clone(),
+            source,
+        })
+    }
+
+    /// index of the `owner/repo` entry in `plugins`, names derived from `url`
+    /// or `path` count like in [`ConfigFile`]
+    fn position(&self, owner: &str, repo: &str) -> Result<Option<usize>, EditError> {
+        let is =
+            |names: Option<(String, String)>| names.is_some_and(|(o, r)| o == owner && r == repo);
+        match self.doc.get("plugins") {
+            None => Ok(None),
+            Some(Item::ArrayOfTables(tables)) => Ok(tables
+                .iter()
+                .position(|t| is(entry_names(|key| t.get(key).and_then(Item::as_str))))),
+            Some(Item::Value(Value::Array(array))) => Ok(array.iter().position(|v| match v {
+                Value::String(spec) => spec
+                    .value()
+                    .parse::<PluginSpec>()
+                    .is_ok_and(|spec| is(Some((spec.owner().into(), spec.repo().into())))),
+                v => v
+                    .as_inline_table()
+                    .is_some_and(|t| is(entry_names(|key| t.get(key).and_then(Value::as_str)))),
+            })),
+            Some(_) => Err(EditError::Malformed(self.path.clone())),
+        }
+    }
+}
+
+impl Display for ConfigEdit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.doc)
+    }
+}
+
+/// `owner/repo` of a plugin table whose fields `get` reads, see
+/// [`Plugins::resolve_names`].
+fn entry_names<'a>(get: impl Fn(&str) -> Option<&'a str>) -> Option<(String, String)> {
+    let mut plugin = Plugins::default();
+    plugin.set_owner(get("owner").unwrap_or_default().into());
+    plugin.set_repo(get("repo").unwrap_or_default().into());
+    plugin.set_url(get("url").map(Into::into));
+    plugin.set_path(get("path").map(PathBuf::from));
+    plugin.resolve_names().ok()?;
+    Some((plugin.owner().to_string(), plugin.repo().to_string()))
+}
+
+/// Pushes `value` onto an inline array, one entry per line when the array
+/// is already laid out that way.
+fn push_inline(array: &mut Array, value: Value) {
+    let multiline = array
+        .iter()
+        .last()
+        .and_then(|v| v.decor().prefix())
+        .and_then(|p| p.as_str())
+        .is_some_and(|p| p.contains('\n'));
+    array.push(value);
+    if multiline && let Some(value) = array.iter_mut().last() {
+        value.decor_mut().set_prefix("\n    ");
+        array.set_trailing_comma(true);
+        array.set_trailing("\n");
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn edit(input: &str) -> ConfigEdit {
+        ConfigEdit::parse(input, Path::new("plugins.toml")).unwrap()
+    }
+
+    #[test]
+    fn add_keeps_comments_and_rejects_duplicates() {
+        let mut config = edit("# my plugins\n[[plugins]]\nowner = \"o\" # mine\nrepo = \"r\"\n");
+        let plugin: PluginSpec = "o/s@dev".parse().unwrap();
+        config.add(&plugin).unwrap();
+        assert_eq!(
+            config.to_string(),
+            "# my plugins\n[[plugins]]\nowner = \"o\" # mine\nrepo = \"r\"\n\n\
+             [[plugins]]\nowner = \"o\"\nrepo = \"s\"\nbranch = \"dev\"\n"
+        );
+        assert!(matches!(
+            config.add(&plugin),
+            Err(EditError::Duplicate(name)) if name == "o/s"
+        ));
+    }
+
+    #[test]
+    fn remove_keeps_the_rest() {
+        let mut config = edit(
+            "[settings]\njobs = 2\n\n[[plugins]]\nowner = \"o\"\nrepo = \"r\"\n\n\
+             # second\n[[plugins]]\nowner = \"o\"\nrepo = \"s\"\n",
+        );
+        config.remove("o", "r").unwrap();
+        assert_eq!(
+            config.to_string(),
+            "[settings]\njobs = 2\n\n# second\n[[plugins]]\nowner = \"o\"\nrepo = \"s\"\n"
+        );
+        assert!(matches!(
+            config.remove("o", "r"),
+            Err(EditError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn edits_inline_arrays() {
+        let mut config = edit("plugins = [\n    { owner = \"o\", repo = \"r\" },\n]\n");
+        config.add(&"o/s".parse().unwrap()).unwrap();
+        assert_eq!(
+            config.to_string(),
+            "plugins = [\n    { owner = \"o\", repo = \"r\" },\n    { owner = \"o\", repo = \"s\" },\n]\n"
+        );
+        config.remove("o", "r").unwrap();
+        assert!(
+            config
+                .to_string()
+                .starts_with("plugins = [\n    { owner = \"o\", repo = \"s\" }")
+        );
+    }
+
+    #[test]
+    fn matches_names_derived_from_url_and_path() {
+        let mut config = edit(
+            "[[plugins]]\nurl = \"git@h:team/theme.git\"\n\n\
+             [[plugins]]\npath = \"~/src/tmux-mine\"\n",
+        );
+        assert!(matches!(
+            config.add(&"team/theme".parse().unwrap()),
+            Err(EditError::Duplicate(name)) if name == "team/theme"
+        ));
+        config.remove("local", "tmux-mine").unwrap();
+        config.remove("team", "theme").unwrap();
+        assert_eq!(config.to_string(), "");
+
+        let mut config = edit("plugins = [{ url = \"file:///srv/o/r.git\" }]\n");
+        config.remove("o", "r").unwrap();
+        assert_eq!(config.to_string(), "plugins = []\n");
+    }
+
+    #[test]
+    fn adds_specs_to_spec_arrays() {
+        let mut config = edit("plugins = [\"o/r\"] # short\n");
+        config
+            .add(&"gitlab.com/o/s@tag:v2".parse().unwrap())
+            .unwrap();
+        assert_eq!(
+            config.to_string(),
+            "plugins = [\"o/r\", \"gitlab.com/o/s@tag:v2\"] # short\n"
+        );
+        assert!(matches!(
+            config.add(&"o/r@dev".parse().unwrap()),
+            Err(EditError::Duplicate(_))
+        ));
+        config.remove("o", "r").unwrap();
+        assert_eq!(
+            config.to_string(),
+            "plugins = [\"gitlab.com/o/s@tag:v2\"] # short\n"
+        );
+    }
+}
@@ -1,3 +1,7 @@
+use std::fmt::Write as _;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -10,4 +14,460 @@ pub enum PluginError {
     Unknown,
     #[error("Can't find config dir")]
     CantFindConfigDir,
+    #[error("{plugin}: {source}")]
+    Git {
+        plugin: String,
+        #[source]
+        source: GitError,
+    },
+    #[error("{plugin}: {source}")]
+    Build {
+        plugin: String,
+        #[source]
+        source: BuildError,
+    },
+}
+
+/// Errors raised by a plugin's `build` hook.
+#[derive(Error, Debug)]
+pub enum BuildError {
+    #[error("can't run build `{command}`: {source}")]
+    Spawn {
+        command: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("build `{command}` failed ({status}){}", last_line(output))]
+    Failed {
+        command: String,
+        status: std::process::ExitStatus,
+        /// stdout followed by stderr
+        output: String,
+    },
+}
+
+/// `: <last line>` of a command's output, to keep one line reports readable.
+fn last_line(output: &str) -> String {
+    output
+        .lines()
+        .rev()
+        .map(str::trim)
+        .find(|l| !l.is_empty())
+        .map(|l| format!(": {}", l))
+        .unwrap_or_default()
+}
+
+/// Errors raised by the `git` commands lazy-tmux runs for a plugin.
+#[derive(Error, Debug)]
+pub enum GitError {
+    #[error("git not found, is it installed and in $PATH?")]
+    NotFound,
+    #[error("failed to run git: {0}")]
+    Spawn(std::io::Error),
+    #[error("already exists at `{}`", .0.display())]
+    AlreadyExists(PathBuf),
+    #[error("can't reach or authenticate with the remote: {stderr}")]
+    Network { stderr: String },
+    #[error("`{0}` is a tag, not a branch, pin it with `tag = \"{0}\"` or `@tag:{0}` instead")]
+    BranchIsTag(String),
+    #[error("no branch `{0}` on the remote")]
+    NoSuchBranch(String),
+    #[error("clone failed: {stderr}")]
+    CloneFailed { stderr: String },
+    #[error("pull conflicts with local changes: {stderr}")]
+    PullConflict { stderr: String },
+    #[error("git {command} failed: {stderr}")]
+    Failed { command: String, stderr: String },
+}
+
+impl GitError {
+    /// Maps a failure to start git, a missing binary included.
+    pub fn spawn(err: std::io::Error) -> Self {
+        match err.kind() {
+            std::io::ErrorKind::NotFound => GitError::NotFound,
+            _ => GitError::Spawn(err),
+        }
+    }
+
+    /// Classifies a failed `git <command>` by what it printed on stderr.
+    pub fn from_stderr(command: &str, stderr: &[u8]) -> Self {
+        let stderr = String::from_utf8_lossy(stderr)
+            .lines()
+            .filter(|l| !l.starts_with("hint:"))
+            .collect::<Vec<_>>()
+            .join("\n")
+            .trim()
+            .to_string();
+        // git follows these with "Could not read from remote repository" too,
+        // so that line says nothing about the network
+        const NETWORK: [&str; 7] = [
+            "Could not resolve host",
+            "unable to access",
+            "Connection refused",
+            "Connection timed out",
+            "Authentication failed",
+            "could not read Username",
+            "Permission denied (publickey",
+        ];
+        const MISSING: [&str; 2] = [
+            "does not appear to be a git repository",
+            "Repository not found",
+        ];
+        const CONFLICT: [&str; 4] = [
+            "CONFLICT",
+            "could not apply",
+            "would be overwritten",
+            "You have unstaged changes",
+        ];
+        let missing = MISSING.iter().any(|m| stderr.contains(m));
+        if !missing && NETWORK.iter().any(|n| stderr.contains(n)) {
+            GitError::Network { stderr }
+        } else if command == "clone" {
+            GitError::CloneFailed { stderr }
+        } else if command == "pull" && CONFLICT.iter().any(|c| stderr.contains(c)) {
+            GitError::PullConflict { stderr }
+        } else {
+            GitError::Failed {
+                command: command.to_string(),
+                stderr,
+            }
+        }
+    }
+}
+
+/// Errors raised while locating, reading or parsing `plugins.toml`.
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Can't find config dir")]
+    CantFindConfigDir,
+    #[error("can't read config file `{}`: {source}", path.display())]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("{}:{}:{}: {}", .0.path.display(), .0.line, .0.column, .0.message)]
+    Parse(Box<Diagnostic>),
+}
+
+impl ConfigError {
+    /// Builds a [`ConfigError::Parse`] from a toml error, resolving its span
+    /// against `input` to find the line, column and offending key.
+    pub fn parse(path: &Path, input: &str, err: &toml::de::Error) -> Self {
+        let span = err.span().unwrap_or(0..0);
+        let message = err.message().trim().to_string();
+
+        if let Some((field, expected)) = unknown_field(&message) {
+            let mut help = suggest(&field, &expected).map(|s| format!("did you mean `{}`?", s));
+            if help.is_none() && !expected.is_empty() {
+                let expected: Vec<_> = expected.iter().map(|e| format!("`{}`", e)).collect();
+                help = Some(format!("expected one of {}", expected.join(", ")));
+            }
+            let mut diagnostic =
+                Diagnostic::new(path, input, span, format!("unknown key `{}`", field));
+            diagnostic.key = Some(field);
+            diagnostic.help = help;
+            return ConfigError::Parse(Box::new(diagnostic));
+        }
+
+        Self::at(path, input, span, message, None)
+    }
+
+    /// Builds a [`ConfigError::Parse`] pointing at `span` in `input`.
+    pub fn at(
+        path: &Path,
+        input: &str,
+        span: Range<usize>,
+        message: impl Into<String>,
+        help: Option<String>,
+    ) -> Self {
+        let mut diagnostic = Diagnostic::new(path, input, span, message.into());
+        diagnostic.help = help;
+        ConfigError::Parse(Box::new(diagnostic))
+    }
+
+    /// Renders the error for the terminal, with a source snippet for parse errors.
+    pub fn render(&self) -> String {
+        match self {
+            ConfigError::Parse(diagnostic) => diagnostic.render(),
+            err => format!("error: {}\n", err),
+        }
+    }
+}
+
+/// Errors raised while parsing a `[host/]owner/repo[@ref]` plugin spec.
+#[derive(Error, Debug, PartialEq)]
+pub enum SpecError {
+    #[error("invalid plugin `{0}`, expected `[host/]owner/repo[@ref]`")]
+    Invalid(String),
+    #[error("empty ref after `@` in `{0}`")]
+    EmptyRef(String),
+    #[error("`{0}` names a commit, pin it with `commit = \"...\"` in a plugin table")]
+    Commit(String),
+}
+
+/// Errors in the load order declared with `after`, `index` is the plugin at fault.
+#[derive(Error, Debug)]
+pub enum OrderError {
+    #[error("`after` names `{after}`, which isn't in the config file")]
+    Unknown {
+        index: usize,
+        after: String,
+        suggestion: Option<String>,
+    },
+    #[error("load order cycle: {}", path.join(" -> "))]
+    Cycle { index: usize, path: Vec<String> },
+}
+
+/// Errors raised by `add` and `remove` while editing `plugins.toml`.
+#[derive(Error, Debug)]
+pub enum EditError {
+    #[error("can't read config file `{}`: {source}", path.display())]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("can't write config file `{}`: {source}", path.display())]
+    Write {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error(transparent)]
+    Spec(#[from] SpecError),
+    #[error("{0} is already in the config file")]
+    Duplicate(String),
+    #[error("{0} isn't in the config file")]
+    NotFound(String),
+    #[error("`plugins` in `{}` isn't an array of tables", .0.display())]
+    Malformed(PathBuf),
+    #[error("can't parse config file `{}`: {source}", path.display())]
+    Toml {
+        path: PathBuf,
+        #[source]
+        source: toml_edit::TomlError,
+    },
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+}
+
+impl EditError {
+    /// Renders the error for the terminal, see [`ConfigError::render`].
+    pub fn render(&self) -> String {
+        match self {
+            EditError::Config(err) => err.render(),
+            err => format!("error: {}\n", err),
+        }
+    }
+}
+
+/// A located problem in the config file.
+#[derive(Debug)]
+pub struct Diagnostic {
+    /// file the error was found in
+    pub path: PathBuf,
+    /// 1-based line number
+    pub line: usize,
+    /// 1-based column number
+    pub column: usize,
+    /// the key the error refers to, when known
+    pub key: Option<String>,
+    /// what went wrong
+    pub message: String,
+    /// a hint on how to fix it
+    pub help: Option<String>,
+    /// a second place in the same file the error involves, its message is
+    /// the label shown there
+    pub related: Option<Box<Diagnostic>>,
+    source_line: String,
+    width: usize,
+}
+
+impl Diagnostic {
+    fn new(path: &Path, input: &str, span: Range<usize>, message: String) -> Self {
+        let (line, column, source_line) = locate(input, span.start);
+        let width = input
+            .get(span)
+            .map(|s| s.lines().next().unwrap_or_default().chars().count())
+            .unwrap_or_default()
+            .max(1);
+        // value errors point at the value, the key sits left of `=`
+        let key = source_line[..byte_column(&source_line, column)]
+            .split_once('=')
+            .map(|(k, _)| k.trim().trim_matches('"').to_string());
+
+        Diagnostic {
+            path: path.to_path_buf(),
+            line,
+            column,
+            key,
+            message,
+            help: None,
+            related: None,
+            source_line,
+            width,
+        }
+    }
+
+    /// Also points at `span` in `input`, labelled with `label`.
+    pub fn relate(&mut self, input: &str, span: Range<usize>, label: impl Into<String>) {
+        self.related = Some(Box::new(Diagnostic::new(
+            &self.path,
+            input,
+            span,
+            label.into(),
+        )));
+    }
+
+    /// Renders the diagnostic in a rustc-like layout.
+    pub fn render(&self) -> String {
+        let last = self.related.as_ref().map_or(0, |r| r.line).max(self.line);
+        let gutter = " ".repeat(last.to_string().len());
+        let mut out = String::new();
+        let _ = writeln!(out, "error: {}", self.message);
+        let _ = writeln!(
+            out,
+            "{}--> {}:{}:{}",
+            gutter,
+            self.path.display(),
+            self.line,
+            self.column
+        );
+        let _ = writeln!(out, "{} |", gutter);
+        let _ = writeln!(
+            out,
+            "{:>w$} | {}",
+            self.line,
+            self.source_line,
+            w = gutter.len()
+        );
+        let _ = writeln!(
+            out,
+            "{} | {}{}",
+            gutter,
+            " ".repeat(self.column - 1),
+            "^".repeat(self.width)
+        );
+        if let Some(related) = &self.related {
+            let _ = writeln!(out, "{} |", gutter);
+            let _ = writeln!(
+                out,
+                "{:>w$} | {}",
+                related.line,
+                related.source_line,
+                w = gutter.len()
+            );
+            let _ = writeln!(
+                out,
+                "{} | {}{} {}",
+                gutter,
+                " ".repeat(related.column - 1),
+                "-".repeat(related.width),
+                related.message
+            );
+        }
+        if let Some(key) = self
+            .key
+            .as_ref()
+            .filter(|k| !self.message.contains(k.as_str()))
+        {
+            let _ = writeln!(out, "{} = note: in key `{}`", gutter, key);
+        }
+        if let Some(help) = &self.help {
+            let _ = writeln!(out, "{} = help: {}", gutter, help);
+        }
+        out
+    }
+}
+
+/// Returns the 1-based line and column of `offset` along with the line's text.
+fn locate(input: &str, offset: usize) -> (usize, usize, String) {
+    let offset = offset.min(input.len());
+    let before = &input[..offset];
+    let line = before.matches('\n').count() + 1;
+    let start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let column = input[start..offset].chars().count() + 1;
+    let text = input[start..]
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .to_string();
+    (line, column, text)
+}
+
+fn byte_column(line: &str, column: usize) -> usize {
+    line.char_indices()
+        .nth(column - 1)
+        .map(|(i, _)| i)
+        .unwrap_or(line.len())
+}
+
+/// Picks apart serde's "unknown field `x`, expected one of `a`, `b`" message.
+fn unknown_field(message: &str) -> Option<(String, Vec<String>)> {
+    if !message.starts_with("unknown field") {
+        return None;
+    }
+    let mut names = message.split('`').skip(1).step_by(2).map(str::to_string);
+    let field = names.next()?;
+    Some((field, names.collect()))
+}
+
+/// Suggests the closest candidate to `name`, if any is close enough.
+pub fn suggest<'a>(name: &str, candidates: &'a [String]) -> Option<&'a str> {
+    candidates
+        .iter()
+        .map(|c| (levenshtein(name, c), c))
+        .filter(|(distance, c)| *distance <= (c.len() / 3).max(1))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, c)| c.as_str())
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            cur[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_git_stderr() {
+        let err = GitError::from_stderr(
+            "clone",
+            b"fatal: unable to access 'https://github.com/o/r.git/': Could not resolve host",
+        );
+        assert!(matches!(err, GitError::Network { .. }));
+        let err = GitError::from_stderr("clone", b"fatal: repository not found");
+        assert!(matches!(err, GitError::CloneFailed { .. }));
+        let err = GitError::from_stderr(
+            "clone",
+            b"fatal: '/srv/r.git' does not appear to be a git repository\n\
+              fatal: Could not read from remote repository.\n",
+        );
+        assert!(matches!(err, GitError::CloneFailed { .. }));
+        let err = GitError::from_stderr(
+            "fetch",
+            b"git@host: Permission denied (publickey).\n\
+              fatal: Could not read from remote repository.\n",
+        );
+        assert!(matches!(err, GitError::Network { .. }));
+        let err = GitError::from_stderr("pull", b"CONFLICT (content): Merge conflict in a.tmux");
+        assert!(matches!(err, GitError::PullConflict { .. }));
+        let err = GitError::from_stderr("fetch", b"fatal: couldn't find remote ref v9");
+        assert_eq!(
+            err.to_string(),
+            "git fetch failed: fatal: couldn't find remote ref v9"
+        );
+    }
 }
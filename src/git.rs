@@ -1,15 +1,43 @@
-#![allow(clippy::print_stdout, clippy::unwrap_used)]
-
-use std::path::PathBuf;
-use std::process::Command;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::time::SystemTime;
 
+use crate::error::GitError;
 use crate::path::PluginDir;
 
+/// The ref a plugin follows (`branch`) or is pinned to (`tag`, `commit`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GitRef<'a> {
+    Branch(&'a str),
+    Tag(&'a str),
+    Commit(&'a str),
+}
+
+impl<'a> GitRef<'a> {
+    /// The branch, tag or commit name itself.
+    pub fn name(&self) -> &'a str {
+        match self {
+            GitRef::Branch(name) | GitRef::Tag(name) | GitRef::Commit(name) => name,
+        }
+    }
+}
+
+impl std::fmt::Display for GitRef<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GitRef::Branch(b) => write!(f, "branch {}", b),
+            GitRef::Tag(t) => write!(f, "tag {}", t),
+            GitRef::Commit(c) => write!(f, "commit {}", c),
+        }
+    }
+}
+
 pub struct Git<'a> {
     owner: &'a str,
     repo: &'a str,
     platform: Option<&'a str>,
-    branch: Option<&'a str>,
+    url: Option<&'a str>,
+    reference: Option<GitRef<'a>>,
 }
 
 #[allow(clippy::should_implement_trait)]
@@ -18,71 +46,411 @@ impl<'a> Git<'a> {
         owner: &'a str,
         repo: &'a str,
         platform: Option<&'a str>,
-        branch: Option<&'a str>,
+        url: Option<&'a str>,
+        reference: Option<GitRef<'a>>,
     ) -> Self {
         Self {
             owner,
             repo,
             platform,
-            branch,
+            url,
+            reference,
         }
     }
 
-    #[inline]
-    pub fn clone(&self) -> Result<(), anyhow::Error> {
-        let dir: PathBuf = PluginDir::builder()
-            .owner(self.owner)
-            .repo(self.repo)
-            .build()
-            .into();
-        if dir.exists() {
-            return Err(anyhow::anyhow!("exists"));
-        }
-        let _branch = self.branch.unwrap_or_default();
-        // let pluginspath: PathBuf = PluginsPathBakOld::new().join(self.repo).join(self.name).into();
-        // // Run the 'git clone' command
-        let d = Command::new("git")
-            .arg("clone")
-            .arg(format!(
+    /// The URL to clone from, `url` when set, else built from the platform.
+    pub fn remote(&self) -> String {
+        match self.url {
+            Some(url) => url.to_string(),
+            None => format!(
                 "https://{}/{}/{}.git",
-                self.platform
-                    .unwrap_or(&std::borrow::Cow::Borrowed("github.com")),
+                self.platform.unwrap_or("github.com"),
                 self.owner,
                 self.repo
-            ))
-            .arg(dir)
-            .arg("--depth=1")
-            .arg("--quiet")
-            .status()
-            .unwrap();
+            ),
+        }
+    }
 
-        if d.success() {
-            println!("Installed {} successfully!", self.repo);
-        } else {
-            eprintln!("Failed to clone repository.");
-            std::process::exit(1); // Exit with a failure status if cloning fails
+    #[inline]
+    pub fn clone(&self) -> Result<(), GitError> {
+        let dir = self.dir();
+        if dir.exists() {
+            return Err(GitError::AlreadyExists(dir));
+        }
+        // clone next to `dir` and move it in place once it's complete, so a
+        // failed clone only ever removes what this process wrote
+        if let Some(GitRef::Branch(branch)) = self.reference {
+            // `--branch` takes a tag too, but then every `pull` fails on it
+            self.require_branch(branch)?;
+        }
+        let tmp = dir.with_file_name(format!(".{}.clone-{}", self.repo, std::process::id()));
+        let _ = std::fs::remove_dir_all(&tmp);
+        let mut cmd = Command::new("git");
+        cmd.arg("clone").arg(self.remote()).arg(&tmp).arg("--quiet");
+        match self.reference {
+            Some(GitRef::Branch(name) | GitRef::Tag(name)) => {
+                cmd.arg("--depth=1").arg("--branch").arg(name);
+            }
+            // abbreviated SHAs can only be resolved against the full history
+            Some(GitRef::Commit(_)) => {}
+            None => {
+                cmd.arg("--depth=1");
+            }
+        }
+        let mut result = check("clone", cmd.output().map_err(GitError::spawn)?);
+        if result.is_ok()
+            && let Some(GitRef::Commit(commit)) = self.reference
+        {
+            result = self.run(&tmp, &["checkout", "-q", "--detach", commit]);
+        }
+        if result.is_ok() && std::fs::rename(&tmp, &dir).is_err() {
+            // another clone of the same plugin got there first
+            result = Err(GitError::AlreadyExists(dir));
         }
 
+        if let Err(err) = result {
+            // don't leave a half-written clone behind for the next run to trip on
+            let _ = std::fs::remove_dir_all(&tmp);
+            return Err(err);
+        }
         Ok(())
     }
 
     #[inline]
-    pub fn pull(&self) -> Result<std::process::ExitStatus, anyhow::Error> {
-        let dir: PathBuf = PluginDir::builder()
+    pub fn pull(&self) -> Result<(), GitError> {
+        let dir = self.dir();
+        if let Some(pin @ (GitRef::Tag(_) | GitRef::Commit(_))) = self.reference {
+            // pinned plugins only move when the pin itself changes
+            if !self.is_at(&dir, pin) {
+                self.checkout_pin(&dir, pin)?;
+            }
+            return Ok(());
+        }
+        let wanted = match self.reference {
+            Some(GitRef::Branch(branch)) => Some(branch.to_string()),
+            _ => self.default_branch(&dir),
+        };
+        if let Some(wanted) = wanted
+            && self.current_branch(&dir).as_deref() != Some(wanted.as_str())
+        {
+            self.switch_branch(&dir, &wanted)?;
+        }
+        // no `--depth` here: a plain fetch into a shallow clone still stops at
+        // the commits it already has, and keeps `old..new` walkable for the changelog
+        let result = self.run(&dir, &["pull", "-q", "-r"]);
+        if let Err(GitError::PullConflict { .. }) = result {
+            // leave the checkout as it was rather than mid-rebase
+            let _ = self.run(&dir, &["rebase", "--abort"]);
+        }
+        result
+    }
+
+    /// The plugin's dir under the plugin root.
+    #[inline]
+    pub fn dir(&self) -> PathBuf {
+        PluginDir::builder()
             .owner(self.owner)
             .repo(self.repo)
             .build()
-            .into();
-        dbg!("pulling: {}", dir.display());
-        let d = Command::new("git")
+            .into()
+    }
+
+    /// Full SHA of the commit checked out in the plugin's dir.
+    #[inline]
+    pub fn head(&self) -> Option<String> {
+        self.rev_parse(&self.dir(), "HEAD")
+    }
+
+    /// Branch checked out in the plugin's dir, `None` on a detached HEAD.
+    #[inline]
+    pub fn branch(&self) -> Option<String> {
+        self.current_branch(&self.dir())
+    }
+
+    /// Whether the working tree has uncommitted or untracked changes.
+    pub fn is_dirty(&self) -> bool {
+        Command::new("git")
             .arg("-C")
-            .arg(dir)
-            .arg("pull")
-            .arg("--depth=1")
-            .arg("-q")
-            .arg("-r")
+            .arg(self.dir())
+            .args(["status", "--porcelain"])
+            .output()
+            .is_ok_and(|out| !out.stdout.is_empty())
+    }
+
+    /// Whether the clone is shallow, i.e. was made with `--depth`.
+    #[inline]
+    pub fn is_shallow(&self) -> bool {
+        self.dir().join(".git").join("shallow").exists()
+    }
+
+    /// When HEAD last moved, read from the mtime of the HEAD reflog.
+    pub fn last_updated(&self) -> Option<SystemTime> {
+        let git_dir = self.dir().join(".git");
+        std::fs::metadata(git_dir.join("logs").join("HEAD"))
+            .or_else(|_| std::fs::metadata(&git_dir))
+            .and_then(|m| m.modified())
+            .ok()
+    }
+
+    /// Fetches the branch `pull` would follow, without touching the working
+    /// tree, and returns how many commits HEAD is behind it along with the
+    /// short SHA and subject of the newest one.
+    pub fn behind(&self) -> Result<(usize, Option<(String, String)>), GitError> {
+        let dir = self.dir();
+        let branch = match self.reference {
+            Some(GitRef::Branch(branch)) => Some(branch.to_string()),
+            _ => self.default_branch(&dir),
+        };
+        let mut fetch = vec!["fetch", "-q", "origin"];
+        if let Some(branch) = &branch {
+            fetch.push(branch);
+        }
+        self.run(&dir, &fetch)?;
+
+        let count = self.read(&dir, &["rev-list", "--count", "HEAD..FETCH_HEAD"])?;
+        let count = count.parse().unwrap_or_default();
+        let newest = self
+            .read(&dir, &["log", "-1", "--format=%h%x09%s", "FETCH_HEAD"])?
+            .split_once('\t')
+            .map(|(sha, subject)| (sha.to_string(), subject.to_string()));
+        Ok((count, newest))
+    }
+
+    /// Whether `to` descends from `from`, false when the history to tell is
+    /// missing, e.g. in a shallow clone.
+    pub fn is_ancestor(&self, from: &str, to: &str) -> bool {
+        Command::new("git")
+            .arg("-C")
+            .arg(self.dir())
+            .args(["merge-base", "--is-ancestor", from, to])
+            .stderr(Stdio::null())
             .status()
-            .map_err(|e| anyhow::anyhow!("Failed to execute git pull: {}", e))?;
-        Ok(d)
+            .is_ok_and(|status| status.success())
+    }
+
+    /// Short SHA and subject of every commit in `from..to`, newest first.
+    pub fn log(&self, from: &str, to: &str) -> Vec<(String, String)> {
+        let Ok(out) = Command::new("git")
+            .arg("-C")
+            .arg(self.dir())
+            .args(["log", "--format=%h%x09%s", &format!("{}..{}", from, to)])
+            .output()
+        else {
+            return Vec::new();
+        };
+        String::from_utf8_lossy(&out.stdout)
+            .lines()
+            .filter_map(|l| l.split_once('\t'))
+            .map(|(sha, subject)| (sha.to_string(), subject.to_string()))
+            .collect()
+    }
+
+    /// Name of the branch currently checked out in `dir`, `None` on a detached HEAD.
+    fn current_branch(&self, dir: &Path) -> Option<String> {
+        let out = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["symbolic-ref", "--short", "-q", "HEAD"])
+            .output()
+            .ok()?;
+        out.status
+            .success()
+            .then(|| String::from_utf8_lossy(&out.stdout).trim().to_string())
+    }
+
+    /// Branch the remote's `HEAD` points at, used when no branch is configured.
+    fn default_branch(&self, dir: &Path) -> Option<String> {
+        let out = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["ls-remote", "--symref", "origin", "HEAD"])
+            .output()
+            .ok()?;
+        // ref: refs/heads/main\tHEAD
+        String::from_utf8_lossy(&out.stdout)
+            .lines()
+            .find_map(|l| l.strip_prefix("ref: refs/heads/"))
+            .and_then(|l| l.split_whitespace().next())
+            .map(str::to_string)
+    }
+
+    /// Whether `name` is a branch or else a tag on the remote, `None` if it's
+    /// neither.
+    pub fn remote_ref<'r>(&self, name: &'r str) -> Result<Option<GitRef<'r>>, GitError> {
+        let (head, tag) = (
+            format!("refs/heads/{}", name),
+            format!("refs/tags/{}", name),
+        );
+        let out = Command::new("git")
+            .args([
+                "ls-remote",
+                "--heads",
+                "--tags",
+                &self.remote(),
+                &head,
+                &tag,
+            ])
+            .output()
+            .map_err(GitError::spawn)?;
+        let stdout = String::from_utf8_lossy(&out.stdout).to_string();
+        check("ls-remote", out)?;
+        // <sha>\trefs/tags/v1, plus <sha>\trefs/tags/v1^{} for annotated tags
+        let refs: Vec<&str> = stdout
+            .lines()
+            .filter_map(|l| l.split('\t').nth(1))
+            .collect();
+        Ok(if refs.contains(&head.as_str()) {
+            Some(GitRef::Branch(name))
+        } else if refs.iter().any(|r| r.trim_end_matches("^{}") == tag) {
+            Some(GitRef::Tag(name))
+        } else {
+            None
+        })
+    }
+
+    /// Fails unless the remote has `branch` under `refs/heads/`.
+    fn require_branch(&self, branch: &str) -> Result<(), GitError> {
+        match self.remote_ref(branch)? {
+            Some(GitRef::Branch(_)) => Ok(()),
+            Some(_) => Err(GitError::BranchIsTag(branch.to_string())),
+            None => Err(GitError::NoSuchBranch(branch.to_string())),
+        }
+    }
+
+    /// Fetches `branch` into a (possibly single-branch, shallow) clone and checks it out.
+    fn switch_branch(&self, dir: &Path, branch: &str) -> Result<(), GitError> {
+        self.require_branch(branch)?;
+        let upstream = format!("origin/{}", branch);
+        self.run(dir, &["remote", "set-branches", "--add", "origin", branch])?;
+        self.run(dir, &["fetch", "--depth=1", "-q", "origin", branch])?;
+        self.run(dir, &["checkout", "-q", "-B", branch, "--track", &upstream])
+    }
+
+    /// Whether `HEAD` in `dir` is the commit `pin` resolves to locally.
+    fn is_at(&self, dir: &Path, pin: GitRef<'_>) -> bool {
+        let head = self.rev_parse(dir, "HEAD");
+        head.is_some() && head == self.rev_parse(dir, &format!("{}^{{commit}}", pin.name()))
+    }
+
+    fn rev_parse(&self, dir: &Path, rev: &str) -> Option<String> {
+        let out = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["rev-parse", "--verify", "-q", rev])
+            .output()
+            .ok()?;
+        out.status
+            .success()
+            .then(|| String::from_utf8_lossy(&out.stdout).trim().to_string())
+    }
+
+    /// Fetches a tag or commit that isn't in the clone yet and checks it out detached.
+    fn checkout_pin(&self, dir: &Path, pin: GitRef<'_>) -> Result<(), GitError> {
+        match pin {
+            GitRef::Tag(tag) => {
+                self.run(dir, &["fetch", "--depth=1", "-q", "origin", "tag", tag])?
+            }
+            _ => {
+                let mut args = vec!["fetch", "-q", "--tags"];
+                if dir.join(".git").join("shallow").exists() {
+                    args.push("--unshallow");
+                }
+                args.extend(["origin", "+refs/heads/*:refs/remotes/origin/*"]);
+                self.run(dir, &args)?
+            }
+        }
+        self.run(dir, &["checkout", "-q", "--detach", pin.name()])
+    }
+
+    /// Runs `git -C dir <args>` and returns its trimmed stdout.
+    fn read(&self, dir: &Path, args: &[&str]) -> Result<String, GitError> {
+        let out = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .output()
+            .map_err(GitError::spawn)?;
+        let stdout = String::from_utf8_lossy(&out.stdout).trim().to_string();
+        check(args.first().copied().unwrap_or_default(), out)?;
+        Ok(stdout)
+    }
+
+    /// Runs `git -C dir <args>`, failing with what git printed on stderr.
+    fn run(&self, dir: &Path, args: &[&str]) -> Result<(), GitError> {
+        let out = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .output()
+            .map_err(GitError::spawn)?;
+        check(args.first().copied().unwrap_or_default(), out)
+    }
+}
+
+/// Derives an `(owner, repo)` pair for the install dir from a git URL: the
+/// last two path components with `.git` stripped, the host standing in for
+/// the owner when the path has a single component.
+///
+/// Handles `scheme://[user@]host[:port]/path`, scp-like `user@host:path` and
+/// plain paths.
+pub fn names_from_url(url: &str) -> Option<(String, String)> {
+    let (host, path) = match url.split_once("://") {
+        Some((_, rest)) => match rest.split_once('/') {
+            Some((host, path)) => (host, path),
+            None => (rest, ""),
+        },
+        None => match url.split_once(':') {
+            Some((host, path)) if !host.contains('/') => (host, path),
+            _ => ("", url),
+        },
+    };
+    let host = host.rsplit('@').next().unwrap_or(host);
+    let host = host.split(':').next().unwrap_or(host);
+    let mut parts = path
+        .split('/')
+        .filter(|p| !p.is_empty())
+        .map(|p| p.strip_suffix(".git").unwrap_or(p));
+    let repo = parts.next_back().filter(|r| !r.is_empty())?;
+    let owner = parts.next_back().unwrap_or(host);
+    if owner.is_empty() {
+        return None;
+    }
+    Some((owner.to_string(), repo.to_string()))
+}
+
+/// Turns the captured output of `git <command>` into a [`GitError`] on failure.
+fn check(command: &str, out: Output) -> Result<(), GitError> {
+    if out.status.success() {
+        Ok(())
+    } else {
+        Err(GitError::from_stderr(command, &out.stderr))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derives_names_from_urls() {
+        let names = |url| names_from_url(url).map(|(o, r)| format!("{}/{}", o, r));
+        assert_eq!(
+            names("https://git.example.com/scm/tools/tmux-yank.git").as_deref(),
+            Some("tools/tmux-yank")
+        );
+        assert_eq!(
+            names("ssh://git@host:2222/me/private.git").as_deref(),
+            Some("me/private")
+        );
+        assert_eq!(
+            names("git@host:me/private.git").as_deref(),
+            Some("me/private")
+        );
+        assert_eq!(
+            names("file:///srv/mirrors/tmux-sensible.git").as_deref(),
+            Some("mirrors/tmux-sensible")
+        );
+        assert_eq!(names("git@host:solo.git").as_deref(), Some("host/solo"));
+        assert_eq!(names("https://host/").as_deref(), None);
     }
 }
@@ -0,0 +1,98 @@
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::when::glob_match;
+
+/// The files an `include` entry of a config file in `base` names, sorted.
+///
+/// `~` is the home dir and relative patterns are relative to `base`. `*` and
+/// `?` expand in any component, matching no file is fine there. A pattern
+/// without them names its file whether it exists or not, so reading it
+/// reports a missing file.
+pub fn resolve(pattern: &str, base: &Path) -> Vec<PathBuf> {
+    let path = match (Path::new(pattern).strip_prefix("~"), dirs::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => base.join(pattern),
+    };
+    if !has_glob(&path.to_string_lossy()) {
+        return vec![path];
+    }
+    let mut found = vec![PathBuf::new()];
+    for component in path.components() {
+        let glob = match component {
+            Component::Normal(name) if has_glob(&name.to_string_lossy()) => name.to_string_lossy(),
+            component => {
+                found.iter_mut().for_each(|f| f.push(component));
+                continue;
+            }
+        };
+        let mut matches = Vec::new();
+        for dir in &found {
+            // a bare relative config path leaves `dir` empty, that's the cwd
+            let read = if dir.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                dir
+            };
+            let Ok(entries) = fs::read_dir(read) else {
+                continue;
+            };
+            let mut names: Vec<String> = entries
+                .filter_map(|e| e.ok()?.file_name().into_string().ok())
+                // like the shell, `*` leaves hidden files alone
+                .filter(|n| !n.starts_with('.') || glob.starts_with('.'))
+                .filter(|n| glob_match(&glob, n))
+                .collect();
+            names.sort();
+            matches.extend(names.into_iter().map(|n| dir.join(n)));
+        }
+        found = matches;
+    }
+    found.retain(|f| f.is_file());
+    found
+}
+
+fn has_glob(s: &str) -> bool {
+    s.contains(['*', '?'])
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_globs_relative_to_base() {
+        let dir = std::env::temp_dir().join(format!("lazy-tmux-include-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("plugins.d/sub.toml")).unwrap();
+        for file in ["b.toml", "a.toml", ".hidden.toml", "notes.md"] {
+            fs::write(dir.join("plugins.d").join(file), "").unwrap();
+        }
+
+        assert_eq!(
+            resolve("plugins.d/*.toml", &dir),
+            [dir.join("plugins.d/a.toml"), dir.join("plugins.d/b.toml")]
+        );
+        assert_eq!(
+            resolve("plugins.?/a.toml", &dir),
+            [dir.join("plugins.d/a.toml")]
+        );
+        assert!(resolve("nothing.d/*.toml", &dir).is_empty());
+        assert_eq!(resolve("base.toml", &dir), [dir.join("base.toml")]);
+        assert_eq!(
+            resolve("/etc/base.toml", &dir),
+            [PathBuf::from("/etc/base.toml")]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn top_level_globs_with_an_empty_base() {
+        // `-c plugins.toml` has an empty parent, tests run in the crate root
+        assert_eq!(
+            resolve("Cargo.t?ml", Path::new("")),
+            [PathBuf::from("Cargo.toml")]
+        );
+    }
+}
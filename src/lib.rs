@@ -5,85 +5,203 @@
     clippy::expect_used,
 )]
 
-use std::process::Command;
 use anyhow::Error;
+use rayon::ThreadPoolBuilder;
 use rayon::iter::{IntoParallelRefIterator as _, ParallelIterator as _};
 
+use self::lock::LockFile;
 use self::path::PluginDir;
 use self::plugins::{ConfigFile, Plugins};
+use self::report::{OperationReport, Outcome, PluginReport};
+use self::status::UpstreamStatus;
 
 pub mod args;
+/// `build` hooks run after install and update
+pub mod build;
+/// all methods regarding cleaning unused plugins
+pub mod clean;
+/// format preserving edits of the config file
+pub mod edit;
 pub mod git;
+/// following `include` in the config file
+pub mod include;
+/// all methods regarding the lockfile
+pub mod lock;
+/// the order `init` sources plugins in
+pub mod order;
 /// all methods regarding plugins path
 pub mod path;
 /// all methods regarding plugins
 pub mod plugins;
+/// per plugin outcomes of install, update and restore
+pub mod report;
+/// finding the scripts `init` runs for each plugin
+pub mod source;
+/// `owner/repo@ref` shorthand for plugins
+pub mod spec;
+/// on disk state of configured plugins
+pub mod status;
+/// per machine conditions on plugins
+pub mod when;
 
 pub mod error;
 
+/// installs every plugin that isn't installed yet, in parallel
+///
+/// every plugin is attempted, failures are recorded in the report
+pub fn install_plugins(config: &ConfigFile) -> OperationReport {
+    for_each_plugin(config, install_one)
+}
 
-pub fn install_plugins() -> Result<(), Error> {
-    if let Some(plugins) = ConfigFile::get_plugins() {
-        plugins
-            .par_iter()
-            .try_for_each(|plugin: &Plugins| -> Result<(), Error> {
-                let dir: PluginDir = plugin.clone().into();
-                if !dir.exists() | is_fully_cloned_repo(plugin.clone().into()) {
-                    plugin.install()?;
-                }
-                Ok(())
-            })?;
+/// same as [`install_plugins`], one plugin at a time
+pub fn install_plugins_one_by_one(config: &ConfigFile) -> OperationReport {
+    config
+        .plugins()
+        .iter()
+        .map(|plugin| PluginReport::new(plugin, with_build(plugin, install_one(plugin))))
+        .collect()
+}
+
+/// installs a single plugin, e.g. one just added with `lazy-tmux add`
+pub fn install_plugin(plugin: &Plugins) -> OperationReport {
+    let outcome = with_build(plugin, install_one(plugin));
+    std::iter::once(PluginReport::new(plugin, outcome)).collect()
+}
+
+fn install_one(plugin: &Plugins) -> Outcome {
+    let dir: PluginDir = plugin.clone().into();
+    if plugin.is_local() {
+        return Outcome::Local;
+    }
+    if dir.exists() {
+        return Outcome::AlreadyPresent;
+    }
+    match plugin.install() {
+        Ok(()) => Outcome::Installed {
+            commit: plugin.head().unwrap_or_default(),
+        },
+        Err(err) => Outcome::failed(&err),
     }
-    Ok(())
 }
 
-pub fn install_plugins_one_by_one() -> Result<(), Error> {
-    let conf = ConfigFile::get_plugins().unwrap();
-    for p in conf {
-        let e: PluginDir = p.clone().into();
-        if !e.exists() | is_fully_cloned_repo(p.clone().into()) {
-            p.install()?;
+/// installs every plugin at the commit recorded in `lock`, moving already
+/// installed ones to it as well
+pub fn install_locked(config: &ConfigFile, lock: &LockFile) -> OperationReport {
+    for_each_plugin(config, |plugin| match lock.get(plugin) {
+        _ if plugin.is_local() => Outcome::Local,
+        None => Outcome::Failed {
+            reason: "not in plugins.lock".to_string(),
+        },
+        Some(entry) => {
+            let dir: PluginDir = plugin.clone().into();
+            if dir.exists() {
+                Outcome::from_head_change(plugin, || plugin.install_at(entry.commit()))
+            } else {
+                match plugin.install_at(entry.commit()) {
+                    Ok(()) => Outcome::Installed {
+                        commit: entry.commit().to_string(),
+                    },
+                    Err(err) => Outcome::failed(&err),
+                }
+            }
         }
-    }
+    })
+}
 
-    Ok(())
+/// resets every installed plugin to the commit recorded in `lock`
+pub fn restore_plugins(config: &ConfigFile, lock: &LockFile) -> OperationReport {
+    for_each_plugin(config, |plugin| {
+        let dir: PluginDir = plugin.clone().into();
+        match lock.get(plugin) {
+            _ if plugin.is_local() => Outcome::Local,
+            _ if !dir.exists() => Outcome::skipped("not installed"),
+            None => Outcome::skipped("not in plugins.lock"),
+            Some(entry) => Outcome::from_head_change(plugin, || plugin.install_at(entry.commit())),
+        }
+    })
 }
 
+/// pulls every installed plugin
+///
+/// every plugin is attempted, failures are recorded in the report
+pub fn update_plugins(config: &ConfigFile) -> OperationReport {
+    for_each_plugin(config, |plugin| {
+        let dir: PluginDir = plugin.clone().into();
+        if plugin.is_local() {
+            Outcome::Local
+        } else if dir.exists() {
+            Outcome::from_head_change(plugin, || plugin.update())
+        } else {
+            Outcome::skipped("not installed")
+        }
+    })
+}
 
-fn is_fully_cloned_repo(mut repo_path: PluginDir) -> bool {
-    let mut git_dir = repo_path.join(".git");
+/// fetches every installed plugin's upstream and reports how far behind it is,
+/// without touching the working trees
+pub fn outdated_plugins(config: &ConfigFile) -> Vec<UpstreamStatus> {
+    par_map(config, UpstreamStatus::check)
+}
 
-    if !git_dir.exists() {
-        return false;
-    }
+/// runs `op` on every plugin in parallel and collects the outcomes in config order
+fn for_each_plugin<F>(config: &ConfigFile, op: F) -> OperationReport
+where
+    F: Fn(&Plugins) -> Outcome + Sync,
+{
+    par_map(config, |plugin| {
+        PluginReport::new(plugin, with_build(plugin, op(plugin)))
+    })
+    .into_iter()
+    .collect()
+}
 
-    // Check if it's a shallow clone
-    if git_dir.join("shallow").exists() {
-        return false;
+/// runs the plugin's `build` hook when `outcome` cloned it or moved its HEAD,
+/// a failing hook turns the outcome into a failure
+fn with_build(plugin: &Plugins, outcome: Outcome) -> Outcome {
+    match outcome {
+        Outcome::Installed { .. } | Outcome::Updated { .. } | Outcome::CheckedOut { .. }
+            if !plugin.build().is_empty() =>
+        {
+            match plugin.run_build() {
+                Ok(_) => outcome,
+                Err(err) => Outcome::failed(&err),
+            }
+        }
+        outcome => outcome,
     }
+}
 
-    // Run `git fsck` to check integrity
-    let status = Command::new("git")
-        .arg("-C")
-        .arg(repo_path.to_string())
-        .arg("fsck")
-        .arg("--full")
-        .status();
-
-    match status {
-        Ok(s) => s.success(),
-        Err(_) => false,
+/// maps `op` over every plugin in parallel, at most `[settings] jobs` at a
+/// time, keeping config order
+fn par_map<T, F>(config: &ConfigFile, op: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(&Plugins) -> T + Sync,
+{
+    let run = || config.plugins().par_iter().map(&op).collect::<Vec<_>>();
+    match config.settings().jobs() {
+        Some(jobs) => match ThreadPoolBuilder::new().num_threads(jobs.get()).build() {
+            Ok(pool) => pool.install(run),
+            Err(_) => run(),
+        },
+        None => run(),
     }
 }
 
-pub fn update_plugins() -> Result<(), Error> {
-    let conf = ConfigFile::get_plugins().unwrap();
-    for p in conf {
-        let e: PluginDir = p.clone().into();
-        if e.exists() | is_fully_cloned_repo(p.clone().into()) {
-            p.update()?;
-        }
-        {}
-    }
-    Ok(())
+/// records the commit of every installed plugin in the lockfile next to the config,
+/// entries of configured plugins that aren't installed here are kept as they were
+///
+/// # Errors
+/// Fails without touching the lockfile if it exists but can't be read or
+/// parsed, or if it can't be written.
+pub fn write_lockfile(config: &ConfigFile) -> Result<(), Error> {
+    let path = LockFile::path(config);
+    let previous = if path.exists() {
+        LockFile::load(&path)?
+    } else {
+        LockFile::default()
+    };
+    let mut lock = LockFile::from_installed(config);
+    lock.keep_missing(config, &previous);
+    lock.save(&path)
 }
@@ -0,0 +1,242 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Error;
+use serde::{Deserialize, Serialize};
+
+use crate::error::ConfigError;
+use crate::plugins::{ConfigFile, DEFAULT_HOST, Plugins};
+
+const HEADER: &str = "# This file is generated by lazy-tmux, do not edit it by hand.\n\n";
+
+/// `plugins.lock`, the exact commit every installed plugin is at.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LockFile {
+    #[serde(default)]
+    plugins: Vec<LockEntry>,
+}
+
+/// A single plugin in the lockfile.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LockEntry {
+    /// left out for plugins cloned from a `url`, which identifies them instead
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    host: Option<String>,
+    owner: String,
+    repo: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(rename = "ref", default, skip_serializing_if = "Option::is_none")]
+    requested: Option<String>,
+    commit: String,
+}
+
+impl LockEntry {
+    /// getter method for host
+    #[inline]
+    pub fn host(&self) -> Option<&str> {
+        self.host.as_deref()
+    }
+
+    /// getter method for owner
+    #[inline]
+    pub fn owner(&self) -> &str {
+        &self.owner
+    }
+
+    /// getter method for repo
+    #[inline]
+    pub fn repo(&self) -> &str {
+        &self.repo
+    }
+
+    /// the git URL the plugin is cloned from, when not derived from the host
+    #[inline]
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+
+    /// the branch, tag or commit asked for in the config, if any
+    #[inline]
+    pub fn requested(&self) -> Option<&str> {
+        self.requested.as_deref()
+    }
+
+    /// the full SHA the plugin resolved to
+    #[inline]
+    pub fn commit(&self) -> &str {
+        &self.commit
+    }
+}
+
+impl LockFile {
+    /// `plugins.lock` next to the config file, e.g. `~/.config/tmux/plugins.lock`
+    #[inline]
+    pub fn path(config: &ConfigFile) -> PathBuf {
+        config.source_path().with_extension("lock")
+    }
+
+    /// reads and parses the lockfile at `path`
+    ///
+    /// # Errors
+    /// Fails if the file can't be read or isn't a valid lockfile.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let input = std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        toml::from_str(&input).map_err(|err| ConfigError::parse(path, &input, &err))
+    }
+
+    /// writes the lockfile to `path`
+    ///
+    /// # Errors
+    /// Fails if the lockfile can't be serialized or written.
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let body = toml::to_string(self)?;
+        std::fs::write(path, format!("{}{}", HEADER, body))?;
+        Ok(())
+    }
+
+    /// records the commit every installed plugin in `config` is at,
+    /// plugins that aren't installed and local plugins are left out
+    pub fn from_installed(config: &ConfigFile) -> Self {
+        let plugins = config
+            .plugins()
+            .iter()
+            .filter(|p| !p.is_local())
+            .filter_map(|p| {
+                Some(LockEntry {
+                    host: match p.url() {
+                        Some(_) => None,
+                        None => Some(p.platform().map_or(DEFAULT_HOST, |h| &**h).to_string()),
+                    },
+                    owner: p.owner().to_string(),
+                    repo: p.repo().to_string(),
+                    url: p.url().map(|u| u.to_string()),
+                    requested: p.git_ref().map(|r| r.to_string()),
+                    commit: p.head()?,
+                })
+            })
+            .collect();
+        Self { plugins }
+    }
+
+    /// copies the entries `previous` has for the plugins `config` lists but
+    /// this lockfile has no commit for: plugins skipped on this machine, not
+    /// installed yet or whose clone failed. The lockfile is shared with the
+    /// machines that do have them.
+    pub fn keep_missing(&mut self, config: &ConfigFile, previous: &LockFile) {
+        let plugins = config.plugins().iter().filter(|p| !p.is_local());
+        for plugin in plugins.chain(config.skipped()) {
+            if self.get(plugin).is_none()
+                && let Some(entry) = previous.get(plugin)
+            {
+                self.plugins.push(entry.clone());
+            }
+        }
+    }
+
+    /// getter method for plugins
+    #[inline]
+    pub fn plugins(&self) -> &[LockEntry] {
+        &self.plugins
+    }
+
+    /// the locked entry for `plugin`, matched on its url or else its host,
+    /// and on owner and repo
+    pub fn get(&self, plugin: &Plugins) -> Option<&LockEntry> {
+        let host = plugin.platform().map_or(DEFAULT_HOST, |h| &**h);
+        self.plugins.iter().find(|e| {
+            let source = match plugin.url() {
+                Some(url) => e.url.as_deref() == Some(&**url),
+                None => e.url.is_none() && e.host.as_deref() == Some(host),
+            };
+            source && e.owner == plugin.owner() && e.repo == plugin.repo()
+        })
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::when::Host;
+
+    #[test]
+    fn round_trips_through_toml() {
+        let lock = LockFile {
+            plugins: vec![LockEntry {
+                host: Some(DEFAULT_HOST.to_string()),
+                owner: "tmux-plugins".to_string(),
+                repo: "tmux-sensible".to_string(),
+                url: None,
+                requested: Some("tag v3.0.0".to_string()),
+                commit: "25cb91f42d020f675bb0a2ce3fbd3a5d96119efa".to_string(),
+            }],
+        };
+        let text = toml::to_string(&lock).unwrap();
+        assert!(text.contains("ref = \"tag v3.0.0\""));
+        assert_eq!(toml::from_str::<LockFile>(&text).unwrap(), lock);
+
+        let plugin = Plugins::new("tmux-plugins".into(), "tmux-sensible".into(), None, None);
+        assert_eq!(lock.get(&plugin), lock.plugins().first());
+    }
+
+    #[test]
+    fn url_plugins_match_on_url() {
+        let entry = LockEntry {
+            host: None,
+            owner: "team".to_string(),
+            repo: "theme".to_string(),
+            url: Some("git@git.corp:team/theme.git".to_string()),
+            requested: None,
+            commit: "25cb91f42d020f675bb0a2ce3fbd3a5d96119efa".to_string(),
+        };
+        let lock = LockFile {
+            plugins: vec![entry.clone()],
+        };
+        let text = toml::to_string(&lock).unwrap();
+        assert!(!text.contains("host"));
+
+        let mut plugin = Plugins::new("team".into(), "theme".into(), None, None);
+        assert_eq!(lock.get(&plugin), None);
+        plugin.set_url(Some("git@git.corp:team/theme.git".into()));
+        assert_eq!(lock.get(&plugin), Some(&entry));
+    }
+
+    #[test]
+    fn keeps_entries_of_skipped_and_missing_plugins() {
+        let input = "[[plugins]]\nowner = \"o\"\nrepo = \"battery\"\nwhen = { os = \"macos\" }\n\
+                     [[plugins]]\nowner = \"o\"\nrepo = \"never-cloned\"\n";
+        let mut config = ConfigFile::parse(input, Path::new("/dots/tmux/plugins.toml")).unwrap();
+        config.skip_unmatched(&Host::new("box", "linux", &[], None));
+        let entry = |repo: &str| LockEntry {
+            host: Some(DEFAULT_HOST.to_string()),
+            owner: "o".to_string(),
+            repo: repo.to_string(),
+            url: None,
+            requested: None,
+            commit: "25cb91f42d020f675bb0a2ce3fbd3a5d96119efa".to_string(),
+        };
+        let previous = LockFile {
+            plugins: vec![entry("battery"), entry("never-cloned"), entry("removed")],
+        };
+
+        let mut lock = LockFile::from_installed(&config);
+        assert!(lock.plugins().is_empty());
+        lock.keep_missing(&config, &previous);
+        assert_eq!(lock.plugins(), [entry("never-cloned"), entry("battery")]);
+    }
+
+    #[test]
+    fn lives_next_to_config() {
+        let config =
+            ConfigFile::parse("plugins = []", Path::new("/dots/tmux/plugins.toml")).unwrap();
+        assert_eq!(
+            LockFile::path(&config),
+            Path::new("/dots/tmux/plugins.lock")
+        );
+    }
+}
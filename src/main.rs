@@ -4,108 +4,596 @@ Parse plugin info	✅ Done
 Clone/install plugins	✅ Done
 Update plugins	✅ Done
 CLI command parsing	✅ Done
-Source .tmux plugins	🟡 Partially
-Clean unused plugins	❌ Not done
-Error handling	🟡 Incomplete
+Source .tmux plugins	✅ Done
+Clean unused plugins	✅ Done
+Error handling	✅ Done
 Logging / verbosity control	❌ Not done
-Tests	❌ Missing
-Parallel install/update	❌ Not done
-Lockfile support	❌ Not done
+Tests	✅ Done
+Parallel install/update	✅ Done
+Lockfile support	✅ Done
 
 */
 
-use std::path::PathBuf;
-use std::process::Command;
+use std::io::Write as _;
+use std::os::unix::process::CommandExt as _;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
 
 use clap::Parser;
-use lazy_tmux::args::Cli;
-use lazy_tmux::plugins::ConfigFile;
-use lazy_tmux::{install_plugins, update_plugins};
-use walkdir::{DirEntry, WalkDir};
+use lazy_tmux::args::{Cli, Commands};
+use lazy_tmux::clean;
+use lazy_tmux::edit::ConfigEdit;
+use lazy_tmux::error::{BuildError, EditError, PluginError};
+use lazy_tmux::lock::LockFile;
 use lazy_tmux::path::PluginDir;
+use lazy_tmux::plugins::{ConfigFile, Plugins};
+use lazy_tmux::report::OperationReport;
+use lazy_tmux::source::{self, LazyKey};
+use lazy_tmux::spec::PluginSpec;
+use lazy_tmux::status::{self, Upstream};
+use lazy_tmux::{
+    install_locked, install_plugin, install_plugins, outdated_plugins, restore_plugins,
+    update_plugins, write_lockfile,
+};
+
+/// server option listing the plugins loaded on a tmux server
+const LOADED_OPTION: &str = "@lazy-tmux-loaded";
 
 fn main() {
     let cli = Cli::parse();
+    let path = match ConfigFile::path(cli.config.as_deref()) {
+        Ok(path) => path,
+        Err(err) => {
+            eprint!("{}", err.render());
+            std::process::exit(1);
+        }
+    };
+    // edit the config before loading it, `add` may be creating it
+    let edited = match &cli.command {
+        Some(Commands::Add {
+            plugin,
+            branch,
+            host,
+            ..
+        }) => add_plugin(&path, plugin, branch.as_deref(), host.as_deref()).map(Some),
+        Some(Commands::Remove { plugin, .. }) => remove_plugin(&path, plugin).map(|()| None),
+        _ => Ok(None),
+    };
+    let added = match edited {
+        Ok(added) => added,
+        Err(err) => {
+            eprint!("{}", err.render());
+            std::process::exit(1);
+        }
+    };
+    let mut config = match ConfigFile::load(&path) {
+        Ok(config) => config,
+        Err(err) => {
+            eprint!("{}", err.render());
+            std::process::exit(1);
+        }
+    };
+    if cli.jobs.is_some() {
+        config.settings_mut().set_jobs(cli.jobs);
+    }
     match cli.command {
-        Some(lazy_tmux::args::Commands::Install) => {
-            let re = install_plugins();
-            match re {
-                Ok(_) => {
-                    dbg!("success");
-                }
-                Err(err) => println!("Error: {}", err),
+        Some(Commands::Add { install, .. }) => {
+            if let Some(plugin) = added.filter(|_| install) {
+                let report = install_plugin(&plugin);
+                save_lockfile(&config);
+                finish(&report);
             }
         }
-        Some(lazy_tmux::args::Commands::Init) => {
-            if install_plugins().is_ok() {
-                source_plugins()
+        Some(Commands::Remove { plugin, clean }) => {
+            if clean && let Err(err) = uninstall_plugin(&config, &plugin) {
+                eprintln!("Failed to clean {}: {}", plugin, err);
+                std::process::exit(1);
             }
         }
-        Some(lazy_tmux::args::Commands::Update) => {
-            println!("update");
-            let re = update_plugins();
-            match re {
+        Some(Commands::Install { locked: true }) => {
+            match LockFile::load(&LockFile::path(&config)) {
+                Ok(lock) => finish(&install_locked(&config, &lock)),
                 Err(err) => {
-                    eprintln!("Failed to update plugins: {}", err);
+                    eprint!("{}", err.render());
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(Commands::Install { locked: false }) => {
+            let report = install_plugins(&config);
+            save_lockfile(&config);
+            finish(&report);
+        }
+        Some(Commands::Init { background: false }) => {
+            // never make tmux wait on the network, what's missing is
+            // installed and sourced by a detached `init --background`
+            source_plugins(&path, config.load_order());
+            if config
+                .plugins()
+                .iter()
+                .any(|p| !p.is_local() && !p.dir().exists())
+                && let Err(err) = spawn_background_init(&path, cli.jobs)
+            {
+                notify(&format!(
+                    "lazy-tmux: can't install missing plugins: {}",
+                    err
+                ));
+            }
+        }
+        Some(Commands::Init { background: true }) => {
+            // runs on every tmux start, so it installs what's locked and
+            // leaves the lockfile to `install` and `update`
+            let lock_path = LockFile::path(&config);
+            let report = if lock_path.exists() {
+                match LockFile::load(&lock_path) {
+                    Ok(lock) => install_locked(&config, &lock),
+                    Err(err) => {
+                        notify(&format!("lazy-tmux: can't read plugins.lock: {}", err));
+                        std::process::exit(1);
+                    }
+                }
+            } else {
+                install_plugins(&config)
+            };
+            let installed = source::freshly_installed(&config, &report);
+            source_plugins(&path, installed.iter().copied());
+            let failed = report
+                .entries()
+                .iter()
+                .filter(|e| e.outcome().is_failure())
+                .count();
+            let mut message = format!("lazy-tmux: installed {} plugin(s)", installed.len());
+            if failed > 0 {
+                message.push_str(&format!(", {} failed, see `lazy-tmux install`", failed));
+            }
+            notify(&message);
+        }
+        Some(Commands::Update { json }) => {
+            let report = update_plugins(&config);
+            save_lockfile(&config);
+            if json {
+                println!("{}", report.to_json());
+                if report.has_failures() {
+                    std::process::exit(1);
                 }
-                Ok(_) => println!("updated"),
+            } else {
+                finish(&report);
+            }
+        }
+        Some(Commands::Restore) => match LockFile::load(&LockFile::path(&config)) {
+            Ok(lock) => finish(&restore_plugins(&config, &lock)),
+            Err(err) => {
+                eprint!("{}", err.render());
+                std::process::exit(1);
+            }
+        },
+        Some(Commands::Build { plugin }) => {
+            if let Err(err) = build_plugin(&config, &plugin) {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Load { plugin, key }) => {
+            if let Err(err) = lazy_load(&config, &plugin, key.as_deref()) {
+                notify(&format!("lazy-tmux: can't load {}: {}", plugin, err));
+                eprintln!("{}", err);
+                std::process::exit(1);
             }
         }
-        Some(lazy_tmux::args::Commands::Clean) => {
-            unimplemented!()
+        Some(Commands::Outdated) => {
+            let statuses = outdated_plugins(&config);
+            print!("{}", status::render_outdated(&statuses));
+            let upstream = || statuses.iter().map(|s| s.upstream());
+            if upstream().any(|u| matches!(u, Upstream::Behind { .. })) {
+                std::process::exit(1);
+            }
+            if upstream().any(|u| matches!(u, Upstream::Failed { .. })) {
+                std::process::exit(2);
+            }
+        }
+        Some(Commands::List { json }) => {
+            let statuses = status::statuses(&config);
+            if json {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&statuses).unwrap_or_default()
+                );
+            } else {
+                print!("{}", status::render(&statuses));
+            }
+        }
+        Some(Commands::Clean { dry_run, yes }) => {
+            if let Err(err) = clean_plugins(&config, dry_run, yes) {
+                eprintln!("Failed to clean plugins: {}", err);
+                std::process::exit(1);
+            }
         }
         None => (),
     }
 }
 
-fn source_plugins() {
-    // we might write it to a log file or something
-    get_tmux_executable().unwrap().iter().for_each(|path| {
-        if path.is_file() {
-            dbg!("Trying to run: {}", path.display());
+/// Prints `report` and exits non-zero if any plugin failed.
+fn finish(report: &OperationReport) {
+    print!("{}", report.render());
+    if report.has_failures() {
+        std::process::exit(1);
+    }
+}
 
-            let _status = Command::new(path.as_os_str()).status().unwrap();
+fn save_lockfile(config: &ConfigFile) {
+    if let Err(err) = write_lockfile(config) {
+        eprintln!("Failed to write plugins.lock: {}", err);
+    }
+}
+
+/// Adds `spec` to the config file at `path` and returns the new entry.
+fn add_plugin(
+    path: &Path,
+    spec: &str,
+    branch: Option<&str>,
+    host: Option<&str>,
+) -> Result<Plugins, EditError> {
+    let mut spec: PluginSpec = spec.parse()?;
+    if host.is_some() {
+        spec.set_host(host.map(str::to_string));
+    }
+    spec.resolve_ref();
+    if branch.is_some() {
+        spec.set_branch(branch.map(str::to_string));
+    }
+    let mut config = ConfigEdit::open(path)?;
+    config.add(&spec)?;
+    config.save()?;
+    println!("Added {} to {}", spec, path.display());
+    Ok(spec.into())
+}
+
+fn remove_plugin(path: &Path, spec: &str) -> Result<(), EditError> {
+    let spec: PluginSpec = spec.parse()?;
+    let mut config = ConfigEdit::open(path)?;
+    config.remove(spec.owner(), spec.repo())?;
+    config.save()?;
+    println!("Removed {} from {}", spec, path.display());
+    Ok(())
+}
+
+/// Reruns the `build` hook of `spec`, printing what it prints.
+fn build_plugin(config: &ConfigFile, spec: &str) -> Result<(), anyhow::Error> {
+    let plugin = find_plugin(config, spec)?;
+    if plugin.build().is_empty() {
+        anyhow::bail!("{} has no build hook", plugin.name());
+    }
+    match plugin.run_build() {
+        Ok(output) => {
+            print!("{}", output);
+            Ok(())
         }
-        // if !status.success() {
-        //     eprintln!("Warning: Failed to source");
-        // } else {
-        //     eprintln!("Success: sourced");
-        // }
-    })
+        Err(err) => {
+            if let PluginError::Build {
+                source: BuildError::Failed { output, .. },
+                ..
+            } = &err
+            {
+                print!("{}", output);
+            }
+            Err(err.into())
+        }
+    }
+}
+
+/// The configured plugin `spec` names, as `owner/repo`.
+fn find_plugin<'a>(config: &'a ConfigFile, spec: &str) -> Result<&'a Plugins, anyhow::Error> {
+    let spec: PluginSpec = spec.parse()?;
+    let is = |p: &&Plugins| p.owner() == spec.owner() && p.repo() == spec.repo();
+    if let Some(plugin) = config.plugins().iter().find(is) {
+        return Ok(plugin);
+    }
+    if config.skipped().iter().any(|p| is(&p)) {
+        anyhow::bail!(
+            "{}/{} is skipped on this machine, see its `when`",
+            spec.owner(),
+            spec.repo()
+        );
+    }
+    anyhow::bail!("{}/{} isn't in the config file", spec.owner(), spec.repo())
 }
 
+/// Deletes the dir of a plugin that was just removed from `config`.
+fn uninstall_plugin(config: &ConfigFile, spec: &str) -> Result<(), anyhow::Error> {
+    let spec: PluginSpec = spec.parse()?;
+    let root = PluginDir::new();
+    let orphans: Vec<_> = clean::find_orphans(config, root.as_path())?
+        .into_iter()
+        .filter(|o| o.owner() == spec.owner() && o.repo() == spec.repo())
+        .collect();
+    clean::remove_orphans(&orphans, root.as_path())?;
+    if orphans.is_empty() {
+        println!("{} wasn't installed", spec);
+    } else {
+        println!("Uninstalled {}", spec);
+    }
+    save_lockfile(config);
+    Ok(())
+}
 
-fn get_tmux_executable() -> Result<Vec<PathBuf>, anyhow::Error> {
-    let mut v = Vec::new();
-    let conf = ConfigFile::get_plugins().unwrap_or_default();
+fn clean_plugins(config: &ConfigFile, dry_run: bool, yes: bool) -> Result<(), anyhow::Error> {
+    let root = PluginDir::new();
+    let orphans = clean::find_orphans(config, root.as_path())?;
+    if orphans.is_empty() {
+        if !dry_run {
+            clean::remove_orphans(&orphans, root.as_path())?;
+        }
+        println!("Nothing to clean");
+        return Ok(());
+    }
 
-    for p in conf {
-        let l: PluginDir = p.into();
-        let walker = WalkDir::new(l.as_path()).into_iter();
-        for entry in walker.filter_entry(|e| !is_hidden(e)) {
-            let e = entry?;
-            if is_tmux_file(&e) {
-                v.push(e.path().to_path_buf());
+    let total: u64 = orphans.iter().map(|o| o.size()).sum();
+    for orphan in &orphans {
+        println!(
+            "{:>8}  {}/{}",
+            clean::human_size(orphan.size()),
+            orphan.owner(),
+            orphan.repo()
+        );
+    }
+    println!(
+        "{:>8}  total, {} unused plugin(s)",
+        clean::human_size(total),
+        orphans.len()
+    );
+
+    if dry_run {
+        return Ok(());
+    }
+    if !yes && !confirm("Remove these plugins?")? {
+        println!("Aborted");
+        return Ok(());
+    }
+    clean::remove_orphans(&orphans, root.as_path())?;
+    println!("Removed {} plugin(s)", orphans.len());
+    Ok(())
+}
+
+fn confirm(prompt: &str) -> std::io::Result<bool> {
+    print!("{} [y/N] ", prompt);
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+/// Sources each installed plugin in `plugins`, except lazy ones that aren't
+/// loaded on this server yet: those get their stub keys and hooks instead,
+/// which run `lazy-tmux load` with `config`.
+fn source_plugins<'a>(config: &Path, plugins: impl IntoIterator<Item = &'a Plugins>) {
+    let mut loaded = loaded_plugins();
+    let known = loaded.len();
+    for plugin in plugins {
+        if !plugin.dir().exists() {
+            continue;
+        }
+        let name = plugin.name();
+        if plugin.is_lazy() && !loaded.contains(&name) {
+            arm_plugin(config, plugin);
+        } else {
+            load_plugin(plugin);
+            if !loaded.contains(&name) {
+                loaded.push(name);
             }
         }
     }
+    // one `set-option` for all of them, every tmux call adds to startup time
+    if loaded.len() > known {
+        save_loaded(&loaded);
+    }
+}
+
+/// Sets the options of `plugin`, then runs its entry points, see
+/// [`source::entry_points`].
+fn load_plugin(plugin: &Plugins) {
+    set_options(plugin);
+    for path in get_tmux_executable(plugin) {
+        if !source::is_executable(&path) {
+            eprintln!("Skipping {}: not an executable file", path.display());
+            continue;
+        }
+        match Command::new(&path).status() {
+            Ok(status) if status.success() => {}
+            Ok(status) => eprintln!("Failed to source {}: {}", path.display(), status),
+            Err(err) => eprintln!("Failed to source {}: {}", path.display(), err),
+        }
+    }
+}
+
+/// Binds each of the plugin's `keys` and appends a hook for each `event`,
+/// all running `lazy-tmux load` for the plugin. A key also replays itself
+/// once the plugin is sourced.
+fn arm_plugin(config: &Path, plugin: &Plugins) {
+    for key in source::lazy_keys(plugin) {
+        let Some(load) = load_command(config, plugin, Some(&key)) else {
+            return;
+        };
+        let load = source::run_shell_arg(&load);
+        tmux(
+            plugin,
+            &["bind-key", "-T", key.table(), key.key(), "run-shell", &load],
+        );
+    }
+    for event in plugin.event() {
+        let Some(load) = load_command(config, plugin, None) else {
+            return;
+        };
+        tmux(
+            plugin,
+            &["set-hook", "-ga", event, &source::hook_command(&load)],
+        );
+    }
+}
+
+/// Drops the stub keys and hooks [`arm_plugin`] set up for `plugin`.
+fn disarm_plugin(plugin: &Plugins) {
+    for stub in source::lazy_keys(plugin) {
+        let _ = Command::new("tmux")
+            .args(["unbind-key", "-T", stub.table(), stub.key()])
+            .stderr(Stdio::null())
+            .status();
+    }
+    if plugin.event().is_empty() {
+        return;
+    }
+    // session hooks and window or pane hooks like `pane-died` are listed apart
+    let mut hooks = String::new();
+    for scope in ["-g", "-gw"] {
+        if let Ok(output) = Command::new("tmux").args(["show-hooks", scope]).output() {
+            hooks.push_str(&String::from_utf8_lossy(&output.stdout));
+        }
+    }
+    for slot in source::hook_slots(&hooks, &source::load_marker(plugin)) {
+        tmux(plugin, &["set-hook", "-gu", slot]);
+    }
+}
+
+/// [`source::load_command`] for this binary and `config`, made absolute.
+fn load_command(config: &Path, plugin: &Plugins, key: Option<&LazyKey>) -> Option<String> {
+    // the stubs run from wherever tmux happens to be
+    let paths = std::env::current_exe().and_then(|exe| Ok((exe, std::path::absolute(config)?)));
+    match paths {
+        Ok((exe, config)) => Some(source::load_command(&exe, &config, plugin, key)),
+        Err(err) => {
+            eprintln!("Can't set up lazy loading for {}: {}", plugin.repo(), err);
+            None
+        }
+    }
+}
 
-    Ok(v)
+/// Sources the plugin `spec` unless it's already loaded on this server,
+/// after dropping its stub keys and hooks. With `key`, then runs whatever
+/// the plugin bound to that key.
+fn lazy_load(config: &ConfigFile, spec: &str, key: Option<&str>) -> Result<(), anyhow::Error> {
+    let plugin = find_plugin(config, spec)?;
+    if !plugin.dir().exists() {
+        anyhow::bail!("{} isn't installed", plugin.name());
+    }
+    disarm_plugin(plugin);
+    let mut loaded = loaded_plugins();
+    let name = plugin.name();
+    if !loaded.contains(&name) {
+        load_plugin(plugin);
+        loaded.push(name);
+        save_loaded(&loaded);
+    }
+
+    let Some(key) = key else {
+        return Ok(());
+    };
+    let key: LazyKey = key.parse().map_err(anyhow::Error::msg)?;
+    let output = Command::new("tmux")
+        .args(["list-keys", "-T", key.table(), key.key()])
+        .stderr(Stdio::null())
+        .output()?;
+    let binding = String::from_utf8_lossy(&output.stdout);
+    match source::bound_command(&binding) {
+        Some(command) => {
+            Command::new("tmux")
+                .args(["if-shell", "-F", "1", command])
+                .status()?;
+        }
+        None => notify(&format!(
+            "lazy-tmux: loaded {}, it doesn't bind {}",
+            plugin.name(),
+            key
+        )),
+    }
+    Ok(())
 }
 
-fn is_hidden(entry: &DirEntry) -> bool {
-    entry
-        .file_name()
-        .to_str()
-        .map(|s| s.starts_with("."))
-        .unwrap_or(false)
+/// Plugins loaded on the running tmux server, kept in the
+/// `@lazy-tmux-loaded` server option so they go away with the server.
+fn loaded_plugins() -> Vec<String> {
+    Command::new("tmux")
+        .args(["show-option", "-gqv", LOADED_OPTION])
+        .stderr(Stdio::null())
+        .output()
+        .map(|o| {
+            String::from_utf8_lossy(&o.stdout)
+                .split_whitespace()
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
 }
 
-fn is_tmux_file(entry: &DirEntry) -> bool {
-    entry
-        .path()
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .map(|ext_str| ext_str == "tmux")
-        .unwrap_or(false)
+/// Records `loaded` as the plugins loaded on the running tmux server.
+fn save_loaded(loaded: &[String]) {
+    let _ = Command::new("tmux")
+        .args(["set-option", "-g", LOADED_OPTION, &loaded.join(" ")])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+}
+
+/// Runs `tmux args`, reporting failures against `plugin`.
+fn tmux(plugin: &Plugins, args: &[&str]) {
+    match Command::new("tmux").args(args).status() {
+        Ok(status) if status.success() => {}
+        Ok(status) => eprintln!(
+            "`tmux {}` failed for {}: {}",
+            args[0],
+            plugin.repo(),
+            status
+        ),
+        Err(err) => eprintln!("`tmux {}` failed for {}: {}", args[0], plugin.repo(), err),
+    }
+}
+
+/// Applies the plugin's `options` with `tmux set-option -g`.
+fn set_options(plugin: &Plugins) {
+    for (name, value) in plugin.options() {
+        let value = value.to_string();
+        match Command::new("tmux")
+            .args(["set-option", "-g", name, &value])
+            .status()
+        {
+            Ok(status) if status.success() => {}
+            Ok(status) => eprintln!("Failed to set {} for {}: {}", name, plugin.repo(), status),
+            Err(err) => eprintln!("Failed to set {} for {}: {}", name, plugin.repo(), err),
+        }
+    }
+}
+
+/// Starts `lazy-tmux init --background` detached from tmux, with its output
+/// discarded so `run` in tmux.conf returns right away.
+fn spawn_background_init(
+    config: &Path,
+    jobs: Option<std::num::NonZeroUsize>,
+) -> std::io::Result<()> {
+    let mut cmd = Command::new(std::env::current_exe()?);
+    cmd.arg("--config").arg(config);
+    if let Some(jobs) = jobs {
+        cmd.arg("--jobs").arg(jobs.to_string());
+    }
+    cmd.args(["init", "--background"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .process_group(0)
+        .spawn()
+        .map(drop)
+}
+
+/// Shows `message` in the tmux status line, if tmux is reachable.
+fn notify(message: &str) {
+    let _ = Command::new("tmux")
+        .args(["display-message", message])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+}
+
+/// Entry points of an installed plugin, nothing if its dir can't be read.
+fn get_tmux_executable(plugin: &Plugins) -> Vec<PathBuf> {
+    source::entry_points(plugin).unwrap_or_else(|err| {
+        eprintln!("Can't read {}: {}", plugin.dir().display(), err);
+        Vec::new()
+    })
 }
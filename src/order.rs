@@ -0,0 +1,111 @@
+use crate::error::OrderError;
+use crate::plugins::Plugins;
+
+/// Indices of `plugins` in the order `init` sources them.
+///
+/// Every plugin comes after the plugins listed in its `after`. Among the
+/// plugins free to go next, a higher `priority` goes first, then config order.
+///
+/// # Errors
+/// Fails if an `after` names a plugin that isn't configured, or if `after`
+/// forms a cycle.
+pub fn load_order(plugins: &[Plugins]) -> Result<Vec<usize>, OrderError> {
+    let names: Vec<String> = plugins.iter().map(Plugins::name).collect();
+    // deps[i]: the plugins `i` loads after
+    let mut deps: Vec<Vec<usize>> = Vec::with_capacity(plugins.len());
+    for (i, plugin) in plugins.iter().enumerate() {
+        let mut after = Vec::new();
+        for dep in plugin.after() {
+            let Some(j) = names.iter().position(|n| **n == **dep) else {
+                return Err(OrderError::Unknown {
+                    index: i,
+                    after: dep.to_string(),
+                    suggestion: crate::error::suggest(dep, &names).map(str::to_string),
+                });
+            };
+            after.push(j);
+        }
+        deps.push(after);
+    }
+
+    let mut order = Vec::with_capacity(plugins.len());
+    let mut done = vec![false; plugins.len()];
+    while order.len() < plugins.len() {
+        let next = (0..plugins.len())
+            .filter(|&i| !done[i] && deps[i].iter().all(|&d| done[d]))
+            .min_by_key(|&i| (std::cmp::Reverse(plugins[i].priority()), i));
+        let Some(next) = next else {
+            let cycle = find_cycle(&deps, &done);
+            return Err(OrderError::Cycle {
+                index: cycle[0],
+                path: cycle.iter().map(|&i| names[i].clone()).collect(),
+            });
+        };
+        done[next] = true;
+        order.push(next);
+    }
+    Ok(order)
+}
+
+/// Walks `after` edges among the plugins left over until one repeats, and
+/// returns the cycle with its first plugin repeated at the end.
+fn find_cycle(deps: &[Vec<usize>], done: &[bool]) -> Vec<usize> {
+    let mut path: Vec<usize> = Vec::new();
+    let mut current = (0..deps.len()).find(|&i| !done[i]).unwrap_or_default();
+    loop {
+        if let Some(start) = path.iter().position(|&i| i == current) {
+            let mut cycle = path.split_off(start);
+            cycle.push(current);
+            return cycle;
+        }
+        path.push(current);
+        // a leftover plugin always waits on another leftover plugin
+        current = deps[current]
+            .iter()
+            .copied()
+            .find(|&d| !done[d])
+            .unwrap_or(current);
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn plugin(repo: &str, after: &[&str], priority: i64) -> Plugins {
+        let mut plugin = Plugins::new("t".into(), repo.into(), None, None);
+        plugin.set_after(after.iter().map(|a| (*a).into()).collect());
+        plugin.set_priority(priority);
+        plugin
+    }
+
+    #[test]
+    fn sorts_by_after_then_priority() {
+        let plugins = [
+            plugin("continuum", &["t/resurrect"], 0),
+            plugin("resurrect", &[], 0),
+            plugin("theme", &[], 10),
+        ];
+        assert_eq!(load_order(&plugins).unwrap(), [2, 1, 0]);
+    }
+
+    #[test]
+    fn reports_cycles_and_unknown_plugins() {
+        let plugins = [
+            plugin("a", &["t/c"], 0),
+            plugin("b", &["t/a"], 0),
+            plugin("c", &["t/b"], 0),
+        ];
+        assert_eq!(
+            load_order(&plugins).unwrap_err().to_string(),
+            "load order cycle: t/a -> t/c -> t/b -> t/a"
+        );
+
+        let plugins = [plugin("a", &["t/bb"], 0)];
+        assert!(matches!(
+            load_order(&plugins),
+            Err(OrderError::Unknown { index: 0, .. })
+        ));
+    }
+}
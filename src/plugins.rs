@@ -7,15 +7,26 @@
     clippy::missing_panics_doc
 )]
 
+use std::collections::BTreeMap;
 use std::fmt::Display;
-use std::path::Path;
+use std::num::NonZeroUsize;
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
 use std::sync::Arc;
 
-use anyhow::Error;
 use dirs::config_local_dir;
-use serde::Deserialize;
+use serde::de::value::{MapAccessDeserializer, SeqAccessDeserializer};
+use serde::de::{self, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+use toml::Spanned;
 
-use crate::git::Git;
+use crate::error::{ConfigError, GitError, OrderError, PluginError};
+use crate::git::{Git, GitRef, names_from_url};
+use crate::path::PluginDir;
+use crate::source::LazyKey;
+use crate::spec::PluginSpec;
+use crate::when::{Host, When};
+use crate::{build, include, order};
 
 /// default host `github.com`
 pub static DEFAULT_HOST: &str = "github.com";
@@ -23,48 +34,370 @@ pub static DEFAULT_HOST: &str = "github.com";
 pub static DEFAULT_OWNER: &str = "abhinandh-s";
 /// default repo `lazy.tmux`
 pub static DEFAULT_REPO: &str = "lazy.tmux";
+/// environment variable used as a fallback for the `--config` flag
+pub static CONFIG_ENV: &str = "LAZY_TMUX_CONFIG";
 
 /// represents a single `Plugin` entry in the ConfigFile
 #[derive(Debug, PartialEq, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Plugins {
+    #[serde(default)]
     owner: Arc<str>,
+    #[serde(default)]
     repo: Arc<str>,
     platform: Option<Arc<str>>,
+    url: Option<Arc<str>>,
+    path: Option<PathBuf>,
     branch: Option<Arc<str>>,
+    tag: Option<Arc<str>>,
+    commit: Option<Arc<str>>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    build: Vec<Arc<str>>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    entry: Vec<Arc<str>>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    after: Vec<Arc<str>>,
+    #[serde(default)]
+    priority: i64,
+    #[serde(default, deserialize_with = "one_or_many")]
+    keys: Vec<Arc<str>>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    event: Vec<Arc<str>>,
+    /// conditions on the machine, the plugin is skipped where they don't hold
+    #[serde(default)]
+    when: When,
+    /// tmux options set before the plugin is sourced, e.g. `@continuum-restore`
+    #[serde(default)]
+    options: BTreeMap<Arc<str>, OptionValue>,
+    /// dir of the config file a local plugin is read from, a relative `path`
+    /// is relative to it
+    #[serde(skip)]
+    base: Option<PathBuf>,
+}
+
+/// The value of a tmux option set from `[plugins.options]`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OptionValue {
+    /// passed as is
+    String(Arc<str>),
+    /// `on` or `off`
+    Bool(bool),
+    /// passed as a decimal number
+    Int(i64),
+}
+
+impl Display for OptionValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            OptionValue::String(s) => f.write_str(s),
+            OptionValue::Bool(b) => f.write_str(if *b { "on" } else { "off" }),
+            OptionValue::Int(i) => write!(f, "{}", i),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for OptionValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ValueVisitor;
+
+        impl Visitor<'_> for ValueVisitor {
+            type Value = OptionValue;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a string, boolean or integer")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                Ok(OptionValue::String(Arc::from(v)))
+            }
+
+            fn visit_bool<E: de::Error>(self, v: bool) -> Result<Self::Value, E> {
+                Ok(OptionValue::Bool(v))
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+                Ok(OptionValue::Int(v))
+            }
+        }
+
+        deserializer.deserialize_any(ValueVisitor)
+    }
 }
 
 impl Plugins {
+    /// the ref the plugin follows or is pinned to, if any
+    #[inline]
+    pub fn git_ref(&self) -> Option<GitRef<'_>> {
+        match (&self.branch, &self.tag, &self.commit) {
+            (Some(branch), _, _) => Some(GitRef::Branch(branch)),
+            (_, Some(tag), _) => Some(GitRef::Tag(tag)),
+            (_, _, Some(commit)) => Some(GitRef::Commit(commit)),
+            _ => None,
+        }
+    }
+
+    /// checks constraints between fields that serde can't express
+    ///
+    /// # Errors
+    ///
+    /// fails if more than one of `branch`, `tag` and `commit` is set, if
+    /// both `url` and `platform` are, if `path` is set along with any
+    /// field that only makes sense for git, if an `entry` leaves the plugin or
+    /// if a key in `keys` can't be parsed, an `event` isn't a hook name or a
+    /// `when` condition is malformed
+    pub fn validate(&self) -> Result<(), (String, &'static str)> {
+        let git = [
+            self.url.as_ref().map(|_| "`url`"),
+            self.platform.as_ref().map(|_| "`platform`"),
+            self.branch.as_ref().map(|_| "`branch`"),
+            self.tag.as_ref().map(|_| "`tag`"),
+            self.commit.as_ref().map(|_| "`commit`"),
+        ];
+        if self.path.is_some()
+            && let Some(field) = git.into_iter().flatten().next()
+        {
+            return Err((
+                format!(
+                    "`path` and {} are mutually exclusive on {}",
+                    field,
+                    self.name()
+                ),
+                "local plugins aren't managed with git, remove it",
+            ));
+        }
+        if let Some(entry) = self.entry.iter().find(|e| {
+            Path::new(&***e)
+                .components()
+                .any(|c| !matches!(c, Component::Normal(_)))
+        }) {
+            return Err((
+                format!(
+                    "entry `{}` of {} must be a relative path inside the plugin",
+                    entry,
+                    self.name()
+                ),
+                "entries are relative to the plugin's directory",
+            ));
+        }
+        if let Err(message) = self
+            .keys
+            .iter()
+            .try_for_each(|k| k.parse::<LazyKey>().map(drop))
+        {
+            return Err((message, "write keys as `[table] key`, e.g. `prefix C-s`"));
+        }
+        if let Some(event) = self
+            .event
+            .iter()
+            .find(|e| e.is_empty() || !e.chars().all(|c| c.is_ascii_lowercase() || c == '-'))
+        {
+            return Err((
+                format!("invalid event `{}` on {}", event, self.name()),
+                "use the name of a tmux hook, e.g. `client-attached`",
+            ));
+        }
+        if let Err(message) = self.when.validate() {
+            return Err((
+                format!("{} on {}", message, self.name()),
+                "conditions look like `tmux = \">=3.2\"` or `env = [\"DISPLAY\", \"TERM=xterm\"]`",
+            ));
+        }
+        if self.url.is_some() && self.platform.is_some() {
+            return Err((
+                format!(
+                    "`url` and `platform` are mutually exclusive on {}",
+                    self.name()
+                ),
+                "keep only one of them",
+            ));
+        }
+        let refs = [
+            self.branch.as_ref().map(|_| "`branch`"),
+            self.tag.as_ref().map(|_| "`tag`"),
+            self.commit.as_ref().map(|_| "`commit`"),
+        ];
+        let set: Vec<&str> = refs.into_iter().flatten().collect();
+        if set.len() > 1 {
+            return Err((
+                format!(
+                    "{} are mutually exclusive on {}",
+                    set.join(" and "),
+                    self.name()
+                ),
+                "keep only one of them",
+            ));
+        }
+        Ok(())
+    }
+
+    /// fills in `owner` and `repo` from `url` or `path` when they're missing,
+    /// local plugins are named `local/<dir name>`
+    ///
+    /// # Errors
+    ///
+    /// fails if neither `owner` and `repo` nor a `url` or `path` to derive them
+    /// from is set
+    pub fn resolve_names(&mut self) -> Result<(), String> {
+        if let Some(path) = &self.path {
+            if self.owner.is_empty() {
+                self.owner = Arc::from("local");
+            }
+            if self.repo.is_empty() {
+                let Some(name) = path.file_name() else {
+                    return Err(format!(
+                        "can't derive a plugin name from `{}`",
+                        path.display()
+                    ));
+                };
+                self.repo = name.to_string_lossy().into();
+            }
+        }
+        if self.owner.is_empty() || self.repo.is_empty() {
+            let Some(url) = &self.url else {
+                let missing = if self.owner.is_empty() {
+                    "owner"
+                } else {
+                    "repo"
+                };
+                return Err(format!("missing field `{}`", missing));
+            };
+            let Some((owner, repo)) = names_from_url(url) else {
+                return Err(format!("can't derive a plugin name from `{}`", url));
+            };
+            if self.owner.is_empty() {
+                self.owner = owner.into();
+            }
+            if self.repo.is_empty() {
+                self.repo = repo.into();
+            }
+        }
+        Ok(())
+    }
+
+    /// the directory of a local plugin, with a leading `~` expanded and a
+    /// relative path taken from the dir of the config file it's in
+    pub fn local_path(&self) -> Option<PathBuf> {
+        let path = self.path.as_deref()?;
+        match (path.strip_prefix("~"), dirs::home_dir()) {
+            (Ok(rest), Some(home)) => Some(home.join(rest)),
+            _ => Some(self.base.as_deref().unwrap_or(Path::new("")).join(path)),
+        }
+    }
+
+    /// whether the plugin is a local directory lazy-tmux leaves alone
+    #[inline]
+    pub fn is_local(&self) -> bool {
+        self.path.is_some()
+    }
+
+    /// where the plugin is sourced from, its local path or its dir under PluginsDir
+    pub fn dir(&self) -> PathBuf {
+        match self.local_path() {
+            Some(path) => path,
+            None => self.git().dir(),
+        }
+    }
+
     /// clones the repo to PluginsDir
     ///
     /// # Errors
     ///
     /// can fail only on clone
     #[inline]
-    pub fn install(&self) -> Result<(), Error> {
+    pub fn install(&self) -> Result<(), PluginError> {
         Git::new(
             &self.owner,
             &self.repo,
             self.platform.as_deref(),
-            self.branch.as_deref(),
+            self.url.as_deref(),
+            self.git_ref(),
         )
-        .clone()?;
-        Ok(())
+        .clone()
+        .map_err(|source| self.git_error(source))
     }
     /// update/pull the repo to PluginsDir
     ///
     /// # Errors
     ///
-    /// can fail only on clone
+    /// can fail only on pull
     #[inline]
-    pub fn update(&self) -> Result<(), Error> {
+    pub fn update(&self) -> Result<(), PluginError> {
         Git::new(
             &self.owner,
             &self.repo,
             self.platform.as_deref(),
-            self.branch.as_deref(),
+            self.url.as_deref(),
+            self.git_ref(),
+        )
+        .pull()
+        .map_err(|source| self.git_error(source))
+    }
+
+    /// clones the repo to PluginsDir at exactly `commit`, or moves an
+    /// existing clone to it
+    ///
+    /// # Errors
+    ///
+    /// fails if the commit can't be fetched or checked out
+    #[inline]
+    pub fn install_at(&self, commit: &str) -> Result<(), PluginError> {
+        let git = Git::new(
+            &self.owner,
+            &self.repo,
+            self.platform.as_deref(),
+            self.url.as_deref(),
+            Some(GitRef::Commit(commit)),
+        );
+        let dir: PluginDir = self.clone().into();
+        if dir.exists() {
+            git.pull()
+        } else {
+            git.clone()
+        }
+        .map_err(|source| self.git_error(source))
+    }
+
+    /// runs the `build` commands in the plugin's dir and returns their output
+    ///
+    /// # Errors
+    ///
+    /// fails on the first command that can't be run or exits non-zero
+    pub fn run_build(&self) -> Result<String, PluginError> {
+        build::run(&self.build, &self.dir()).map_err(|source| PluginError::Build {
+            plugin: self.name(),
+            source,
+        })
+    }
+
+    fn git_error(&self, source: GitError) -> PluginError {
+        PluginError::Git {
+            plugin: self.name(),
+            source,
+        }
+    }
+
+    /// the `Git` handle for this plugin's repo
+    #[inline]
+    pub fn git(&self) -> Git<'_> {
+        Git::new(
+            &self.owner,
+            &self.repo,
+            self.platform.as_deref(),
+            self.url.as_deref(),
+            self.git_ref(),
         )
-        .pull()?;
-        Ok(())
+    }
+
+    /// full SHA of the installed commit, `None` when not installed
+    #[inline]
+    pub fn head(&self) -> Option<String> {
+        self.git().head()
+    }
+
+    /// short SHA and subject of every commit in `from..to`, newest first
+    #[inline]
+    pub fn log(&self, from: &str, to: &str) -> Vec<(String, String)> {
+        self.git().log(from, to)
     }
 }
 
@@ -74,7 +407,20 @@ impl Default for Plugins {
             owner: Arc::from(DEFAULT_OWNER),
             repo: Arc::from(DEFAULT_REPO),
             platform: Some(Arc::from(DEFAULT_HOST)),
+            url: None,
+            path: None,
             branch: None,
+            tag: None,
+            commit: None,
+            build: Vec::new(),
+            entry: Vec::new(),
+            after: Vec::new(),
+            priority: 0,
+            keys: Vec::new(),
+            event: Vec::new(),
+            when: When::default(),
+            options: BTreeMap::new(),
+            base: None,
         }
     }
 }
@@ -88,7 +434,17 @@ impl Display for Plugins {
             self.repo,
             self.platform.as_deref().unwrap_or(DEFAULT_HOST),
             self.branch.as_deref().unwrap_or("none"),
-        )
+        )?;
+        if let Some(url) = &self.url {
+            write!(f, "\n  url: {}", url)?;
+        }
+        if let Some(path) = &self.path {
+            write!(f, "\n  path: {}", path.display())?;
+        }
+        match self.git_ref() {
+            Some(pin @ (GitRef::Tag(_) | GitRef::Commit(_))) => write!(f, "\n  pinned: {}", pin),
+            _ => Ok(()),
+        }
     }
 }
 
@@ -104,7 +460,20 @@ impl Plugins {
             owner,
             repo,
             platform,
+            url: None,
+            path: None,
             branch,
+            tag: None,
+            commit: None,
+            build: Vec::new(),
+            entry: Vec::new(),
+            after: Vec::new(),
+            priority: 0,
+            keys: Vec::new(),
+            event: Vec::new(),
+            when: When::default(),
+            options: BTreeMap::new(),
+            base: None,
         }
     }
 
@@ -120,6 +489,12 @@ impl Plugins {
         self.owner = owner;
     }
 
+    /// `owner/repo`, the name reports, `load`, `after` and the lockfile use
+    #[inline]
+    pub fn name(&self) -> String {
+        format!("{}/{}", self.owner, self.repo)
+    }
+
     #[inline]
     /// getter method for repo
     pub fn repo(&self) -> &str {
@@ -144,6 +519,36 @@ impl Plugins {
         self.platform = platform;
     }
 
+    /// getter method for url
+    #[inline]
+    pub fn url(&self) -> Option<&Arc<str>> {
+        self.url.as_ref()
+    }
+
+    /// setter method for url
+    #[inline]
+    pub fn set_url(&mut self, url: Option<Arc<str>>) {
+        self.url = url;
+    }
+
+    /// getter method for path, as written in the config
+    #[inline]
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// setter method for path
+    #[inline]
+    pub fn set_path(&mut self, path: Option<PathBuf>) {
+        self.path = path;
+    }
+
+    /// setter method for base, the dir a relative `path` is relative to
+    #[inline]
+    pub fn set_base(&mut self, base: Option<PathBuf>) {
+        self.base = base;
+    }
+
     /// getter method for branch
     #[inline]
     pub fn branch(&self) -> Option<&Arc<str>> {
@@ -155,42 +560,843 @@ impl Plugins {
     pub fn set_branch(&mut self, branch: Option<Arc<str>>) {
         self.branch = branch;
     }
+
+    /// getter method for tag
+    #[inline]
+    pub fn tag(&self) -> Option<&Arc<str>> {
+        self.tag.as_ref()
+    }
+
+    /// setter method for tag
+    #[inline]
+    pub fn set_tag(&mut self, tag: Option<Arc<str>>) {
+        self.tag = tag;
+    }
+
+    /// getter method for commit
+    #[inline]
+    pub fn commit(&self) -> Option<&Arc<str>> {
+        self.commit.as_ref()
+    }
+
+    /// setter method for commit
+    #[inline]
+    pub fn set_commit(&mut self, commit: Option<Arc<str>>) {
+        self.commit = commit;
+    }
+
+    /// commands run after the plugin is cloned or its HEAD moves
+    #[inline]
+    pub fn build(&self) -> &[Arc<str>] {
+        &self.build
+    }
+
+    /// setter method for build
+    #[inline]
+    pub fn set_build(&mut self, build: Vec<Arc<str>>) {
+        self.build = build;
+    }
+
+    /// scripts `init` runs instead of the top level `*.tmux` files
+    #[inline]
+    pub fn entry(&self) -> &[Arc<str>] {
+        &self.entry
+    }
+
+    /// setter method for entry
+    #[inline]
+    pub fn set_entry(&mut self, entry: Vec<Arc<str>>) {
+        self.entry = entry;
+    }
+
+    /// `owner/repo` of the plugins this one is sourced after
+    #[inline]
+    pub fn after(&self) -> &[Arc<str>] {
+        &self.after
+    }
+
+    /// setter method for after
+    #[inline]
+    pub fn set_after(&mut self, after: Vec<Arc<str>>) {
+        self.after = after;
+    }
+
+    /// plugins with a higher priority are sourced first, `0` by default
+    #[inline]
+    pub fn priority(&self) -> i64 {
+        self.priority
+    }
+
+    /// setter method for priority
+    #[inline]
+    pub fn set_priority(&mut self, priority: i64) {
+        self.priority = priority;
+    }
+
+    /// keys that load the plugin on first press instead of `init` sourcing it
+    #[inline]
+    pub fn keys(&self) -> &[Arc<str>] {
+        &self.keys
+    }
+
+    /// setter method for keys
+    #[inline]
+    pub fn set_keys(&mut self, keys: Vec<Arc<str>>) {
+        self.keys = keys;
+    }
+
+    /// tmux hooks that load the plugin the first time they run
+    #[inline]
+    pub fn event(&self) -> &[Arc<str>] {
+        &self.event
+    }
+
+    /// setter method for event
+    #[inline]
+    pub fn set_event(&mut self, event: Vec<Arc<str>>) {
+        self.event = event;
+    }
+
+    /// getter method for when
+    #[inline]
+    pub fn when(&self) -> &When {
+        &self.when
+    }
+
+    /// setter method for when
+    #[inline]
+    pub fn set_when(&mut self, when: When) {
+        self.when = when;
+    }
+
+    /// whether `init` leaves sourcing the plugin to its `keys` or `event`
+    #[inline]
+    pub fn is_lazy(&self) -> bool {
+        !self.keys.is_empty() || !self.event.is_empty()
+    }
+
+    /// tmux options `init` sets before sourcing the plugin, sorted by name
+    #[inline]
+    pub fn options(&self) -> &BTreeMap<Arc<str>, OptionValue> {
+        &self.options
+    }
+
+    /// setter method for options
+    #[inline]
+    pub fn set_options(&mut self, options: BTreeMap<Arc<str>, OptionValue>) {
+        self.options = options;
+    }
+}
+
+/// Accepts `build = "make"` as well as `build = ["make", "make install"]`.
+pub(crate) fn one_or_many<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Arc<str>>, D::Error> {
+    struct OneOrMany;
+
+    impl<'de> Visitor<'de> for OneOrMany {
+        type Value = Vec<Arc<str>>;
+
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            f.write_str("a command or a list of commands")
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+            Ok(vec![Arc::from(v)])
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+            Vec::deserialize(SeqAccessDeserializer::new(seq))
+        }
+    }
+
+    deserializer.deserialize_any(OneOrMany)
+}
+
+/// the `[settings]` table of the config file
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Settings {
+    jobs: Option<NonZeroUsize>,
+}
+
+impl Settings {
+    /// how many git operations may run at once, `None` lets rayon decide
+    #[inline]
+    pub fn jobs(&self) -> Option<NonZeroUsize> {
+        self.jobs
+    }
+
+    /// setter method for jobs
+    #[inline]
+    pub fn set_jobs(&mut self, jobs: Option<NonZeroUsize>) {
+        self.jobs = jobs;
+    }
+
+    /// takes every setting `other` sets
+    fn merge(&mut self, other: Settings) {
+        if other.jobs.is_some() {
+            self.jobs = other.jobs;
+        }
+    }
 }
 
 /// `ConfigFile` represents the entire list of `Plugins` parsed from config file.
-#[derive(Debug, PartialEq, Deserialize)]
+#[derive(Debug, Default, PartialEq)]
 pub struct ConfigFile {
     plugins: Vec<Plugins>,
+    /// indices into `plugins`, see [`order::load_order`]
+    order: Vec<usize>,
+    /// plugins whose `when` doesn't hold on this machine
+    skipped: Vec<Plugins>,
+    settings: Settings,
+    source: PathBuf,
+}
+
+/// The config file as written, with the span of every plugin table kept for
+/// diagnostics on checks that run after deserializing.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawConfig {
+    /// files whose plugins and settings come before this file's own
+    #[serde(default)]
+    include: Vec<Spanned<String>>,
+    #[serde(default)]
+    settings: Settings,
+    #[serde(default)]
+    plugins: Vec<Spanned<PluginEntry>>,
+}
+
+/// A config file and everything it includes, merged.
+#[derive(Default)]
+struct Merged {
+    /// path and contents of every file read, for diagnostics
+    sources: Vec<(PathBuf, String)>,
+    /// each plugin with the source it came from and its span there
+    plugins: Vec<(Plugins, usize, Range<usize>)>,
+    settings: Settings,
+}
+
+impl Merged {
+    /// Merges the config file `input` read from `path`: its includes in order,
+    /// then its own settings and plugins. A plugin replaces an earlier one with
+    /// the same owner and repo in place. `stack` holds the files being read.
+    fn read(
+        &mut self,
+        input: &str,
+        path: &Path,
+        stack: &mut Vec<(PathBuf, PathBuf)>,
+    ) -> Result<(), ConfigError> {
+        let raw: RawConfig =
+            toml::from_str(input).map_err(|err| ConfigError::parse(path, input, &err))?;
+        stack.push((canonical(path), path.to_path_buf()));
+        let base = path.parent().unwrap_or(Path::new(""));
+        for include in raw.include {
+            let span = include.span();
+            for file in include::resolve(include.get_ref(), base) {
+                let key = canonical(&file);
+                if let Some(start) = stack.iter().position(|(p, _)| *p == key) {
+                    let cycle: Vec<String> = stack[start..]
+                        .iter()
+                        .map(|(_, shown)| shown.display().to_string())
+                        .chain([file.display().to_string()])
+                        .collect();
+                    return Err(ConfigError::at(
+                        path,
+                        input,
+                        span,
+                        format!("include cycle: {}", cycle.join(" -> ")),
+                        Some("remove one of the includes".to_string()),
+                    ));
+                }
+                let included = std::fs::read_to_string(&file).map_err(|err| {
+                    ConfigError::at(
+                        path,
+                        input,
+                        span.clone(),
+                        format!("can't read included file {}: {}", file.display(), err),
+                        None,
+                    )
+                })?;
+                self.read(&included, &file, stack)?;
+            }
+        }
+        stack.pop();
+
+        let source = self.sources.len();
+        self.sources.push((path.to_path_buf(), input.to_string()));
+        self.settings.merge(raw.settings);
+        for plugin in raw.plugins {
+            let span = plugin.span();
+            let PluginEntry(mut plugin) = plugin.into_inner();
+            plugin.resolve_names().map_err(|message| {
+                ConfigError::at(
+                    path,
+                    input,
+                    span.clone(),
+                    message,
+                    Some("set `owner` and `repo`, or a `url` to derive them from".to_string()),
+                )
+            })?;
+            plugin.validate().map_err(|(message, help)| {
+                ConfigError::at(path, input, span.clone(), message, Some(help.to_string()))
+            })?;
+            if plugin.is_local() {
+                // like `include`, not wherever tmux happened to be started
+                plugin.set_base(Some(base.to_path_buf()));
+            }
+            let same = |(p, _, _): &&mut (Plugins, usize, Range<usize>)| {
+                p.owner == plugin.owner && p.repo == plugin.repo
+            };
+            match self.plugins.iter_mut().find(same) {
+                // only a later file overrides, e.g. two urls ending in the same
+                // `owner/repo` in one file would silently replace each other
+                Some((_, first, first_span)) if *first == source => {
+                    let mut err = ConfigError::at(
+                        path,
+                        input,
+                        span,
+                        format!("`{}` is listed twice", plugin.name()),
+                        Some(
+                            "remove one, or set `owner` and `repo` to tell them apart".to_string(),
+                        ),
+                    );
+                    if let ConfigError::Parse(diagnostic) = &mut err {
+                        diagnostic.relate(input, first_span.clone(), "first listed here");
+                    }
+                    return Err(err);
+                }
+                Some(slot) => *slot = (plugin, source, span),
+                None => self.plugins.push((plugin, source, span)),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `path` resolved on disk, as it is if it doesn't exist.
+fn canonical(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// A `plugins` entry, either a table or a [`PluginSpec`] string.
+struct PluginEntry(Plugins);
+
+impl<'de> Deserialize<'de> for PluginEntry {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct EntryVisitor;
+
+        impl<'de> Visitor<'de> for EntryVisitor {
+            type Value = PluginEntry;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a plugin table or a `[host/]owner/repo[@ref]` string")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                let spec: PluginSpec = v.parse().map_err(E::custom)?;
+                Ok(PluginEntry(spec.into()))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                Plugins::deserialize(MapAccessDeserializer::new(map)).map(PluginEntry)
+            }
+        }
+
+        deserializer.deserialize_any(EntryVisitor)
+    }
 }
 
 impl ConfigFile {
+    /// resolves the config file to use
+    ///
+    /// the `custom` path (the `--config` flag) wins, then `$LAZY_TMUX_CONFIG`,
+    /// then the default `$CONFIG_HOME/tmux/plugins.toml`
+    ///
+    /// # Errors
+    ///
+    /// fails if no custom path is given and the config dir can't be found
+    #[inline]
+    pub fn path(custom: Option<&Path>) -> Result<PathBuf, ConfigError> {
+        if let Some(path) = custom {
+            return Ok(path.to_path_buf());
+        }
+        if let Some(path) = std::env::var_os(CONFIG_ENV).filter(|v| !v.is_empty()) {
+            return Ok(PathBuf::from(path));
+        }
+        config_local_dir()
+            .map(|dir| dir.join("tmux").join("plugins.toml"))
+            .ok_or(ConfigError::CantFindConfigDir)
+    }
+
+    /// reads and parses the config file at `path`, then skips the plugins
+    /// whose `when` doesn't hold on this machine
+    ///
+    /// # Errors
+    ///
+    /// fails if the file can't be read or isn't a valid config, the error
+    /// carries the line, column and key at fault
+    #[inline]
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let input = std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let mut config = Self::parse(&input, path)?;
+        config.skip_unmatched(&Host::current());
+        Ok(config)
+    }
+
+    /// parses `input` as a config file read from `path`, merging in the files
+    /// it includes, see [`include::resolve`]
+    ///
+    /// # Errors
+    ///
+    /// fails if `input` or a file it includes isn't a valid config, if an
+    /// included file can't be read or if includes form a cycle, the error
+    /// points into the file at fault
+    #[inline]
+    pub fn parse(input: &str, path: &Path) -> Result<Self, ConfigError> {
+        let mut merged = Merged::default();
+        merged.read(input, path, &mut Vec::new())?;
+        let Merged {
+            sources,
+            plugins,
+            settings,
+        } = merged;
+        let (plugins, origins): (Vec<Plugins>, Vec<(usize, Range<usize>)>) = plugins
+            .into_iter()
+            .map(|(plugin, source, span)| (plugin, (source, span)))
+            .unzip();
+        let order = order::load_order(&plugins).map_err(|err| {
+            let (index, help) = match &err {
+                OrderError::Unknown {
+                    index, suggestion, ..
+                } => (
+                    *index,
+                    suggestion
+                        .as_ref()
+                        .map(|s| format!("did you mean `{}`?", s)),
+                ),
+                OrderError::Cycle { index, .. } => (
+                    *index,
+                    Some("remove one of the `after` entries".to_string()),
+                ),
+            };
+            let (source, span) = &origins[index];
+            let (path, input) = &sources[*source];
+            ConfigError::at(path, input, span.clone(), err.to_string(), help)
+        })?;
+        Ok(Self {
+            plugins,
+            order,
+            skipped: Vec::new(),
+            settings,
+            source: path.to_path_buf(),
+        })
+    }
+
+    /// getter method for settings
+    #[inline]
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// mutable access to settings, for command line overrides
+    #[inline]
+    pub fn settings_mut(&mut self) -> &mut Settings {
+        &mut self.settings
+    }
+
+    /// the file this config was parsed from
+    #[inline]
+    pub fn source_path(&self) -> &Path {
+        &self.source
+    }
+
+    /// getter method for plugins
+    #[inline]
+    pub fn plugins(&self) -> &[Plugins] {
+        &self.plugins
+    }
+
+    /// plugins left out because their `when` doesn't hold, they're neither
+    /// installed nor sourced but still count as configured
+    #[inline]
+    pub fn skipped(&self) -> &[Plugins] {
+        &self.skipped
+    }
+
+    /// moves the plugins whose `when` doesn't hold on `host` to
+    /// [`ConfigFile::skipped`], the rest keep their load order
+    pub fn skip_unmatched(&mut self, host: &Host) {
+        let keep: Vec<bool> = self.plugins.iter().map(|p| p.when.matches(host)).collect();
+        // new index of each plugin that's kept
+        let mut index = Vec::with_capacity(keep.len());
+        let mut kept = 0;
+        for &k in &keep {
+            index.push(kept);
+            kept += usize::from(k);
+        }
+        self.order = self
+            .order
+            .iter()
+            .filter(|&&i| keep[i])
+            .map(|&i| index[i])
+            .collect();
+        let (plugins, skipped) = std::mem::take(&mut self.plugins)
+            .into_iter()
+            .zip(keep)
+            .partition::<Vec<_>, _>(|(_, k)| *k);
+        self.plugins = plugins.into_iter().map(|(p, _)| p).collect();
+        self.skipped.extend(skipped.into_iter().map(|(p, _)| p));
+    }
+
+    /// plugins in the order `init` sources them, honouring `after` and `priority`
+    pub fn load_order(&self) -> impl Iterator<Item = &Plugins> {
+        self.order.iter().filter_map(|&i| self.plugins.get(i))
+    }
+
     /// parses the config file `$CONFIG_HOME/tmux/plugins.toml`
-    /// returns some vector of `Plugin` entries
+    /// (or `$LAZY_TMUX_CONFIG` when set)
+    /// returns a vector of `Plugin` entries
+    ///
+    /// # Errors
+    ///
+    /// see [`ConfigFile::load`]
     #[inline]
-    pub fn get_plugins() -> Option<Vec<Plugins>> {
-        let config_dir = config_local_dir()?.join("tmux").join("plugins.toml");
-        let input = std::fs::read_to_string(config_dir).ok()?;
-        let config: ConfigFile = toml::from_str(&input).ok()?;
-        Some(config.plugins)
+    pub fn get_plugins() -> Result<Vec<Plugins>, ConfigError> {
+        Self::load(&Self::path(None)?).map(|config| config.plugins)
     }
 
     /// lists all plugins parsed from the config file
     #[inline]
     #[allow(clippy::print_stdout)]
-    pub fn list_plugins() {
-        if let Some(plugins) = Self::get_plugins() {
-            plugins.iter().for_each(|p| {
-                println!("{}", p);
-            });
-        }
+    pub fn list_plugins(&self) {
+        self.plugins.iter().for_each(|p| {
+            println!("{}", p);
+        });
     }
 
     /// for test porpose
+    ///
+    /// # Errors
+    ///
+    /// see [`ConfigFile::load`]
     #[inline]
-    pub fn get_plugins_from(path: &Path) -> Option<Vec<Plugins>> {
-        let config_dir = path.to_path_buf();
-        let input = std::fs::read_to_string(config_dir).ok()?;
-        let config: ConfigFile = toml::from_str(&input).ok()?;
-        Some(config.plugins)
+    pub fn get_plugins_from(path: &Path) -> Result<Vec<Plugins>, ConfigError> {
+        Self::load(path).map(|config| config.plugins)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_path_wins() {
+        let custom = Path::new("/tmp/dotfiles/plugins.toml");
+        assert_eq!(ConfigFile::path(Some(custom)).unwrap(), custom);
+    }
+
+    #[test]
+    fn load_reads_plugins() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/plugins.toml");
+        let config = ConfigFile::load(&path).unwrap();
+        assert_eq!(config.plugins(), [Plugins::default()]);
+    }
+
+    #[test]
+    fn unknown_key_suggests_field() {
+        let input = "[[plugins]]\nowner = \"tmux-plugins\"\nrepoo = \"tmux-sensible\"\n";
+        let Err(ConfigError::Parse(d)) = ConfigFile::parse(input, Path::new("plugins.toml")) else {
+            panic!("expected a parse error");
+        };
+        assert_eq!((d.line, d.column), (3, 1));
+        assert_eq!(d.key.as_deref(), Some("repoo"));
+        assert_eq!(d.help.as_deref(), Some("did you mean `repo`?"));
+    }
+
+    #[test]
+    fn pins_are_exclusive_with_branch() {
+        let input = "[[plugins]]\nowner = \"o\"\nrepo = \"r\"\n\n[[plugins]]\nowner = \"o\"\nrepo = \"s\"\nbranch = \"dev\"\ntag = \"v1.2.0\"\n";
+        let Err(ConfigError::Parse(d)) = ConfigFile::parse(input, Path::new("plugins.toml")) else {
+            panic!("expected a parse error");
+        };
+        assert_eq!((d.line, d.column), (5, 1));
+        assert_eq!(
+            d.message,
+            "`branch` and `tag` are mutually exclusive on o/s"
+        );
+
+        let config = ConfigFile::parse(
+            "[[plugins]]\nowner = \"o\"\nrepo = \"r\"\ncommit = \"abc123\"\n",
+            Path::new("plugins.toml"),
+        )
+        .unwrap();
+        assert_eq!(
+            config.plugins()[0].git_ref(),
+            Some(GitRef::Commit("abc123"))
+        );
+    }
+
+    #[test]
+    fn reads_settings() {
+        let input = "[settings]\njobs = 4\n\n[[plugins]]\nowner = \"o\"\nrepo = \"r\"\n";
+        let config = ConfigFile::parse(input, Path::new("plugins.toml")).unwrap();
+        assert_eq!(config.settings().jobs(), NonZeroUsize::new(4));
+
+        let input = "[settings]\njobs = 0\n\n[[plugins]]\nowner = \"o\"\nrepo = \"r\"\n";
+        assert!(ConfigFile::parse(input, Path::new("plugins.toml")).is_err());
+    }
+
+    #[test]
+    fn mixes_specs_and_tables() {
+        let input = "plugins = [\"tmux-plugins/tmux-sensible\", \"gitlab.com/foo/bar@tag:v2\", { owner = \"o\", repo = \"r\" }]\n";
+        let config = ConfigFile::parse(input, Path::new("plugins.toml")).unwrap();
+        let plugins = config.plugins();
+        assert_eq!(plugins.len(), 3);
+        assert_eq!(plugins[1].platform().map(|h| &**h), Some("gitlab.com"));
+        assert_eq!(plugins[1].git_ref(), Some(GitRef::Tag("v2")));
+        assert_eq!(plugins[2].repo(), "r");
+
+        let input = "plugins = [\"tmux-sensible\"]\n";
+        let Err(ConfigError::Parse(d)) = ConfigFile::parse(input, Path::new("plugins.toml")) else {
+            panic!("expected a parse error");
+        };
+        assert_eq!((d.line, d.column), (1, 12));
+    }
+
+    #[test]
+    fn derives_names_from_url() {
+        let input = "[[plugins]]\nurl = \"git@git.example.com:team/tmux-theme.git\"\n\n[[plugins]]\nrepo = \"r\"\n";
+        let Err(ConfigError::Parse(d)) = ConfigFile::parse(input, Path::new("plugins.toml")) else {
+            panic!("expected a parse error");
+        };
+        assert_eq!((d.line, d.message.as_str()), (4, "missing field `owner`"));
+
+        let input = "[[plugins]]\nurl = \"git@git.example.com:team/tmux-theme.git\"\n";
+        let config = ConfigFile::parse(input, Path::new("plugins.toml")).unwrap();
+        let plugin = &config.plugins()[0];
+        assert_eq!((plugin.owner(), plugin.repo()), ("team", "tmux-theme"));
+        assert_eq!(plugin.name(), "team/tmux-theme");
+        assert_eq!(
+            plugin.git().remote(),
+            "git@git.example.com:team/tmux-theme.git"
+        );
+    }
+
+    #[test]
+    fn names_listed_twice_in_one_file() {
+        let input = "plugins = [\n  { url = \"git@a:team/theme.git\" },\n  { url = \"git@b:team/theme.git\" },\n]\n";
+        let Err(ConfigError::Parse(d)) = ConfigFile::parse(input, Path::new("plugins.toml")) else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(d.message, "`team/theme` is listed twice");
+        assert_eq!(d.line, 3);
+        assert_eq!(d.related.as_ref().map(|r| r.line), Some(2));
+        assert!(d.render().contains("first listed here"));
+    }
+
+    #[test]
+    fn local_plugins_skip_git_fields() {
+        let input = "[[plugins]]\npath = \"~/src/tmux-dev\"\n";
+        let config = ConfigFile::parse(input, Path::new("plugins.toml")).unwrap();
+        let plugin = &config.plugins()[0];
+        assert_eq!((plugin.owner(), plugin.repo()), ("local", "tmux-dev"));
+        assert_eq!(
+            plugin.local_path(),
+            dirs::home_dir().map(|h| h.join("src/tmux-dev"))
+        );
+
+        let input = "[[plugins]]\npath = \"mine\"\n[[plugins]]\npath = \"/src/theirs\"\n";
+        let config = ConfigFile::parse(input, Path::new("/dots/tmux/plugins.toml")).unwrap();
+        assert_eq!(
+            config.plugins()[0].local_path(),
+            Some(PathBuf::from("/dots/tmux/mine"))
+        );
+        assert_eq!(
+            config.plugins()[1].local_path(),
+            Some(PathBuf::from("/src/theirs"))
+        );
+
+        let input = "[[plugins]]\npath = \"/src/tmux-dev\"\nbranch = \"main\"\n";
+        let Err(ConfigError::Parse(d)) = ConfigFile::parse(input, Path::new("plugins.toml")) else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(
+            d.message,
+            "`path` and `branch` are mutually exclusive on local/tmux-dev"
+        );
+    }
+
+    #[test]
+    fn build_is_one_or_many() {
+        let input = "plugins = [\n  { owner = \"o\", repo = \"r\", build = \"make\" },\n  { owner = \"o\", repo = \"s\", build = [\"npm ci\", \"npm run build\"] },\n]\n";
+        let config = ConfigFile::parse(input, Path::new("plugins.toml")).unwrap();
+        assert_eq!(config.plugins()[0].build(), [Arc::from("make")]);
+        assert_eq!(config.plugins()[1].build().len(), 2);
+
+        let input = "[[plugins]]\nowner = \"o\"\nrepo = \"r\"\nbuild = 1\n";
+        let Err(ConfigError::Parse(d)) = ConfigFile::parse(input, Path::new("plugins.toml")) else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(d.key.as_deref(), Some("build"));
+    }
+
+    #[test]
+    fn entries_stay_inside_the_plugin() {
+        let input = "[[plugins]]\nowner = \"o\"\nrepo = \"r\"\nentry = \"../x.tmux\"\n";
+        let Err(ConfigError::Parse(d)) = ConfigFile::parse(input, Path::new("plugins.toml")) else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(
+            d.message,
+            "entry `../x.tmux` of o/r must be a relative path inside the plugin"
+        );
+    }
+
+    #[test]
+    fn keys_must_name_a_table_and_key() {
+        let input = "[[plugins]]\nowner = \"o\"\nrepo = \"r\"\nkeys = \"C-s\"\n";
+        let config = ConfigFile::parse(input, Path::new("plugins.toml")).unwrap();
+        assert_eq!(config.plugins()[0].keys(), ["C-s".into()]);
+
+        let input = "[[plugins]]\nowner = \"o\"\nrepo = \"r\"\nkeys = [\"prefix C-s x\"]\n";
+        let Err(ConfigError::Parse(d)) = ConfigFile::parse(input, Path::new("plugins.toml")) else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(
+            d.message,
+            "invalid key `prefix C-s x`, expected `[table] key`"
+        );
+    }
+
+    #[test]
+    fn events_are_hook_names() {
+        let input = "[[plugins]]\nowner = \"o\"\nrepo = \"r\"\nevent = \"client-attached\"\n";
+        let config = ConfigFile::parse(input, Path::new("plugins.toml")).unwrap();
+        assert!(config.plugins()[0].is_lazy());
+
+        let input = "[[plugins]]\nowner = \"o\"\nrepo = \"r\"\nevent = [\"client attached\"]\n";
+        let Err(ConfigError::Parse(d)) = ConfigFile::parse(input, Path::new("plugins.toml")) else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(d.message, "invalid event `client attached` on o/r");
+    }
+
+    #[test]
+    fn skips_plugins_whose_when_fails() {
+        let input = "[[plugins]]\nowner = \"t\"\nrepo = \"yank\"\nafter = \"t/battery\"\n\n\
+                     [[plugins]]\nowner = \"t\"\nrepo = \"battery\"\nwhen = { os = \"macos\" }\n\n\
+                     [[plugins]]\nowner = \"t\"\nrepo = \"theme\"\npriority = 1\n\n\
+                     [plugins.when]\nhostname = \"work-*\"\ntmux = \">=3.2\"\n";
+        let mut config = ConfigFile::parse(input, Path::new("plugins.toml")).unwrap();
+        config.skip_unmatched(&Host::new("work-1", "linux", &[], "3.3a".parse().ok()));
+        let order: Vec<&str> = config.load_order().map(|p| &*p.repo).collect();
+        assert_eq!(order, ["theme", "yank"]);
+        assert_eq!(config.skipped().len(), 1);
+        assert_eq!(&*config.skipped()[0].repo, "battery");
+
+        let input = "[[plugins]]\nowner = \"o\"\nrepo = \"r\"\nwhen = { tmux = \"3\" }\n";
+        let Err(ConfigError::Parse(d)) = ConfigFile::parse(input, Path::new("plugins.toml")) else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(d.message, "invalid tmux version constraint `3` on o/r");
+    }
+
+    #[test]
+    fn includes_merge_in_order() {
+        let dir = std::env::temp_dir().join(format!("lazy-tmux-includes-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("plugins.d")).unwrap();
+        let write = |file: &str, input: &str| std::fs::write(dir.join(file), input).unwrap();
+        write(
+            "base.toml",
+            "[settings]\njobs = 2\n\n[[plugins]]\nowner = \"t\"\nrepo = \"a\"\n\n\
+             [[plugins]]\nowner = \"t\"\nrepo = \"b\"\n",
+        );
+        write("plugins.d/mine.toml", "plugins = [\"t/c\", \"t/a@dev\"]\n");
+        let input = "include = [\"base.toml\", \"plugins.d/*.toml\"]\n\n[settings]\njobs = 4\n";
+        let config = ConfigFile::parse(input, &dir.join("plugins.toml")).unwrap();
+        let plugins: Vec<String> = config
+            .plugins()
+            .iter()
+            .map(|p| format!("{}@{}", p.repo, p.branch.as_deref().unwrap_or("-")))
+            .collect();
+        assert_eq!(plugins, ["a@dev", "b@-", "c@-"]);
+        assert_eq!(config.settings().jobs(), NonZeroUsize::new(4));
+
+        write("plugins.d/mine.toml", "plugins = [\"t/c\", \"nope\"]\n");
+        let Err(ConfigError::Parse(d)) = ConfigFile::parse(input, &dir.join("plugins.toml")) else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(
+            (d.path.clone(), d.line),
+            (dir.join("plugins.d/mine.toml"), 1)
+        );
+
+        write("base.toml", "include = [\"plugins.toml\"]\n");
+        write("plugins.toml", input);
+        let Err(ConfigError::Parse(d)) = ConfigFile::load(&dir.join("plugins.toml")) else {
+            panic!("expected a parse error");
+        };
+        let root = dir.join("plugins.toml").display().to_string();
+        let base = dir.join("base.toml").display().to_string();
+        assert_eq!(
+            d.message,
+            format!("include cycle: {} -> {} -> {}", root, base, root)
+        );
+        assert_eq!(d.path, dir.join("base.toml"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn load_order_cycles_are_config_errors() {
+        let input = "[[plugins]]\nowner = \"t\"\nrepo = \"a\"\nafter = \"t/b\"\n\n[[plugins]]\nowner = \"t\"\nrepo = \"b\"\nafter = [\"t/a\"]\n";
+        let Err(ConfigError::Parse(d)) = ConfigFile::parse(input, Path::new("plugins.toml")) else {
+            panic!("expected a parse error");
+        };
+        assert_eq!((d.line, d.column), (1, 1));
+        assert_eq!(d.message, "load order cycle: t/a -> t/b -> t/a");
+    }
+
+    #[test]
+    fn options_are_typed() {
+        let input = "[[plugins]]\nowner = \"o\"\nrepo = \"r\"\n\n[plugins.options]\n\"@continuum-restore\" = true\n\"@continuum-save-interval\" = 15\n\"@resurrect-strategy-nvim\" = \"session\"\n";
+        let config = ConfigFile::parse(input, Path::new("plugins.toml")).unwrap();
+        let options: Vec<(String, String)> = config.plugins()[0]
+            .options()
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        assert_eq!(
+            options,
+            [
+                ("@continuum-restore".to_string(), "on".to_string()),
+                ("@continuum-save-interval".to_string(), "15".to_string()),
+                (
+                    "@resurrect-strategy-nvim".to_string(),
+                    "session".to_string()
+                ),
+            ]
+        );
+
+        let input = "[[plugins]]\nowner = \"o\"\nrepo = \"r\"\noptions = { \"@x\" = 1.5 }\n";
+        let Err(ConfigError::Parse(d)) = ConfigFile::parse(input, Path::new("plugins.toml")) else {
+            panic!("expected a parse error");
+        };
+        assert!(d.message.contains("a string, boolean or integer"));
+    }
+
+    #[test]
+    fn type_error_names_key() {
+        let input = "[[plugins]]\nowner = 42\nrepo = \"tmux-sensible\"\n";
+        let Err(ConfigError::Parse(d)) = ConfigFile::parse(input, Path::new("plugins.toml")) else {
+            panic!("expected a parse error");
+        };
+        assert_eq!((d.line, d.column), (2, 9));
+        assert_eq!(d.key.as_deref(), Some("owner"));
     }
 }
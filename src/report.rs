@@ -0,0 +1,314 @@
+use std::fmt::{Display, Write as _};
+
+use serde::Serialize;
+
+use crate::error::PluginError;
+use crate::plugins::Plugins;
+
+/// A commit pulled in by an update.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Commit {
+    /// short SHA
+    pub sha: String,
+    /// first line of the commit message
+    pub subject: String,
+}
+
+/// What happened to a single plugin during an install, update or restore.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Outcome {
+    /// freshly cloned at `commit`
+    Installed { commit: String },
+    /// nothing to do, the plugin dir already exists
+    AlreadyPresent,
+    /// HEAD moved from `from` to `to`, bringing in `commits` (newest first)
+    Updated {
+        from: String,
+        to: String,
+        commits: Vec<Commit>,
+    },
+    /// HEAD moved from `from` to `to`, which doesn't descend from it, e.g.
+    /// an older pin or another branch
+    CheckedOut { from: String, to: String },
+    /// HEAD didn't move
+    UpToDate,
+    /// a local plugin, never touched
+    Local,
+    /// not attempted, with the reason why
+    Skipped { reason: String },
+    /// attempted and failed, with the reason why
+    Failed { reason: String },
+}
+
+impl Outcome {
+    /// runs `op` on an installed plugin and reports how its HEAD moved, only
+    /// a move forward lists new commits
+    pub fn from_head_change(
+        plugin: &Plugins,
+        op: impl FnOnce() -> Result<(), PluginError>,
+    ) -> Self {
+        let from = plugin.head().unwrap_or_default();
+        if let Err(err) = op() {
+            return Outcome::failed(&err);
+        }
+        let to = plugin.head().unwrap_or_default();
+        if from == to {
+            return Outcome::UpToDate;
+        }
+        if !plugin.git().is_ancestor(&from, &to) {
+            return Outcome::CheckedOut { from, to };
+        }
+        let commits = plugin
+            .log(&from, &to)
+            .into_iter()
+            .map(|(sha, subject)| Commit { sha, subject })
+            .collect();
+        Outcome::Updated { from, to, commits }
+    }
+
+    /// a skipped plugin
+    pub fn skipped(reason: impl Into<String>) -> Self {
+        Outcome::Skipped {
+            reason: reason.into(),
+        }
+    }
+
+    /// a failure, without repeating the plugin name the report already shows
+    pub fn failed(err: &PluginError) -> Self {
+        let reason = match err {
+            PluginError::Git { source, .. } => source.to_string(),
+            PluginError::Build { source, .. } => source.to_string(),
+            err => err.to_string(),
+        };
+        Outcome::Failed { reason }
+    }
+
+    /// whether this outcome should make the command exit non-zero
+    #[inline]
+    pub fn is_failure(&self) -> bool {
+        matches!(self, Outcome::Failed { .. })
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Outcome::Installed { .. } => "installed",
+            Outcome::AlreadyPresent => "already present",
+            Outcome::Updated { .. } => "updated",
+            Outcome::CheckedOut { .. } => "checked out",
+            Outcome::UpToDate => "up to date",
+            Outcome::Local => "local",
+            Outcome::Skipped { .. } => "skipped",
+            Outcome::Failed { .. } => "failed",
+        }
+    }
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Outcome::Installed { commit } if !commit.is_empty() => {
+                write!(f, "installed at {}", short(commit))
+            }
+            Outcome::Updated { from, to, commits } => write!(
+                f,
+                "updated {} -> {} ({} new commit{})",
+                short(from),
+                short(to),
+                commits.len(),
+                if commits.len() == 1 { "" } else { "s" }
+            ),
+            Outcome::CheckedOut { from, to } => {
+                write!(f, "checked out {} -> {}", short(from), short(to))
+            }
+            Outcome::Skipped { reason } => write!(f, "skipped: {}", reason),
+            Outcome::Failed { reason } => write!(f, "failed: {}", reason),
+            outcome => f.write_str(outcome.label()),
+        }
+    }
+}
+
+/// The outcome of an operation for one plugin.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PluginReport {
+    plugin: String,
+    #[serde(flatten)]
+    outcome: Outcome,
+}
+
+impl PluginReport {
+    /// method new
+    pub fn new(plugin: &Plugins, outcome: Outcome) -> Self {
+        Self {
+            plugin: plugin.name(),
+            outcome,
+        }
+    }
+
+    /// `owner/repo` of the plugin
+    #[inline]
+    pub fn plugin(&self) -> &str {
+        &self.plugin
+    }
+
+    /// getter method for outcome
+    #[inline]
+    pub fn outcome(&self) -> &Outcome {
+        &self.outcome
+    }
+}
+
+/// Per plugin outcomes of an install, update or restore, in config order.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct OperationReport {
+    #[serde(rename = "plugins")]
+    entries: Vec<PluginReport>,
+}
+
+impl FromIterator<PluginReport> for OperationReport {
+    fn from_iter<T: IntoIterator<Item = PluginReport>>(iter: T) -> Self {
+        Self {
+            entries: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl OperationReport {
+    /// getter method for entries
+    #[inline]
+    pub fn entries(&self) -> &[PluginReport] {
+        &self.entries
+    }
+
+    /// whether any plugin failed
+    #[inline]
+    pub fn has_failures(&self) -> bool {
+        self.entries.iter().any(|e| e.outcome.is_failure())
+    }
+
+    /// renders the report as a table followed by a one line summary
+    pub fn render(&self) -> String {
+        let width = self
+            .entries
+            .iter()
+            .map(|e| e.plugin.len())
+            .max()
+            .unwrap_or_default()
+            .max("PLUGIN".len());
+        let mut out = String::new();
+        let _ = writeln!(out, "{:<width$}  STATUS", "PLUGIN");
+        for entry in &self.entries {
+            // git's stderr runs over several lines, the table keeps the first
+            // and `--json` all of them
+            let status = entry.outcome.to_string();
+            let status = status.lines().next().unwrap_or_default();
+            let _ = writeln!(out, "{:<width$}  {}", entry.plugin, status);
+        }
+
+        let mut counts: Vec<(&str, usize)> = Vec::new();
+        for entry in &self.entries {
+            let label = entry.outcome.label();
+            match counts.iter_mut().find(|(l, _)| *l == label) {
+                Some((_, n)) => *n += 1,
+                None => counts.push((label, 1)),
+            }
+        }
+        let summary: Vec<String> = counts
+            .iter()
+            .map(|(label, n)| format!("{} {}", n, label))
+            .collect();
+        let _ = writeln!(
+            out,
+            "\n{} plugin(s): {}",
+            self.entries.len(),
+            summary.join(", ")
+        );
+
+        for entry in &self.entries {
+            if let Outcome::Updated { commits, .. } = &entry.outcome
+                && !commits.is_empty()
+            {
+                let _ = writeln!(out, "\n{}:", entry.plugin);
+                for commit in commits {
+                    let _ = writeln!(out, "  {} {}", commit.sha, commit.subject);
+                }
+            }
+        }
+        out
+    }
+
+    /// renders the report as pretty printed JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+}
+
+fn short(commit: &str) -> &str {
+    commit.get(..7).unwrap_or(commit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_table_and_summary() {
+        let plugin = Plugins::default();
+        let report: OperationReport = [
+            PluginReport::new(
+                &plugin,
+                Outcome::Updated {
+                    from: "1bac27dafa7565fc05b6".to_string(),
+                    to: "ed80f9d0b8e1d3a2c4f5".to_string(),
+                    commits: vec![
+                        Commit {
+                            sha: "ed80f9d".to_string(),
+                            subject: "fix status line".to_string(),
+                        },
+                        Commit {
+                            sha: "3c1e2aa".to_string(),
+                            subject: "add option".to_string(),
+                        },
+                    ],
+                },
+            ),
+            PluginReport::new(
+                &plugin,
+                Outcome::Failed {
+                    reason: "clone failed: fatal: no repo\nfatal: Could not read from remote\n\n\
+                             Please make sure you have the correct access rights"
+                        .to_string(),
+                },
+            ),
+            PluginReport::new(&plugin, Outcome::AlreadyPresent),
+        ]
+        .into_iter()
+        .collect();
+
+        assert!(report.has_failures());
+        assert_eq!(
+            report.render(),
+            "PLUGIN                 STATUS\n\
+             abhinandh-s/lazy.tmux  updated 1bac27d -> ed80f9d (2 new commits)\n\
+             abhinandh-s/lazy.tmux  failed: clone failed: fatal: no repo\n\
+             abhinandh-s/lazy.tmux  already present\n\
+             \n\
+             3 plugin(s): 1 updated, 1 failed, 1 already present\n\
+             \n\
+             abhinandh-s/lazy.tmux:\n  \
+             ed80f9d fix status line\n  \
+             3c1e2aa add option\n"
+        );
+    }
+
+    #[test]
+    fn serializes_status_tags() {
+        let report: OperationReport = [PluginReport::new(&Plugins::default(), Outcome::UpToDate)]
+            .into_iter()
+            .collect();
+        assert_eq!(
+            serde_json::to_string(&report).unwrap_or_default(),
+            r#"{"plugins":[{"plugin":"abhinandh-s/lazy.tmux","status":"up_to_date"}]}"#
+        );
+    }
+}
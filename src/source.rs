@@ -0,0 +1,328 @@
+use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::os::unix::fs::PermissionsExt as _;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::plugins::{ConfigFile, Plugins};
+use crate::report::{OperationReport, Outcome};
+
+/// The scripts `init` runs for `plugin`, in the order it runs them.
+///
+/// Follows TPM: every executable `*.tmux` file at the top of the plugin's dir,
+/// sorted by name. Files listed in the plugin's `entry` replace that lookup and
+/// keep their listed order.
+///
+/// # Errors
+/// Fails if the plugin's dir can't be read.
+pub fn entry_points(plugin: &Plugins) -> io::Result<Vec<PathBuf>> {
+    let dir = plugin.dir();
+    if !plugin.entry().is_empty() {
+        return Ok(plugin.entry().iter().map(|e| dir.join(&**e)).collect());
+    }
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(&dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "tmux") && is_executable(&path) {
+            entries.push(path);
+        }
+    }
+    entries.sort();
+    Ok(entries)
+}
+
+/// The plugins of `config` that `report` says were just cloned, in the order
+/// `init` sources them. `report` has an entry per plugin, in config order.
+pub fn freshly_installed<'a>(config: &'a ConfigFile, report: &OperationReport) -> Vec<&'a Plugins> {
+    let fresh: Vec<&Plugins> = config
+        .plugins()
+        .iter()
+        .zip(report.entries())
+        .filter(|(_, e)| matches!(e.outcome(), Outcome::Installed { .. }))
+        .map(|(p, _)| p)
+        .collect();
+    config.load_order().filter(|p| fresh.contains(p)).collect()
+}
+
+/// Whether `path` is a regular file with an executable bit set.
+pub fn is_executable(path: &Path) -> bool {
+    fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+/// A key that loads a lazy plugin on first press, written `[table] key`,
+/// e.g. `prefix C-s` or `root M-u`. The table defaults to `prefix`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LazyKey {
+    table: String,
+    key: String,
+}
+
+impl LazyKey {
+    /// the key table the key is bound in
+    #[inline]
+    pub fn table(&self) -> &str {
+        &self.table
+    }
+
+    /// the key itself, as tmux names it
+    #[inline]
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+impl FromStr for LazyKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (table, key) = match s.split_whitespace().collect::<Vec<_>>()[..] {
+            [key] => ("prefix", key),
+            [table, key] => (table, key),
+            _ => return Err(format!("invalid key `{}`, expected `[table] key`", s)),
+        };
+        Ok(Self {
+            table: table.to_string(),
+            key: key.to_string(),
+        })
+    }
+}
+
+impl Display for LazyKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.table, self.key)
+    }
+}
+
+/// The keys of a lazy loaded plugin, empty if it's sourced by `init`.
+pub fn lazy_keys(plugin: &Plugins) -> Vec<LazyKey> {
+    plugin
+        .keys()
+        .iter()
+        .filter_map(|k| k.parse().ok())
+        .collect()
+}
+
+/// `'<exe>' --config '<config>' load '<owner/repo>' [--key '<key>']`, the
+/// `sh` command a stub key or hook runs to load `plugin`.
+pub fn load_command(exe: &Path, config: &Path, plugin: &Plugins, key: Option<&LazyKey>) -> String {
+    let mut load = format!(
+        "{} --config {} load {}",
+        shell_quote(&exe.to_string_lossy()),
+        shell_quote(&config.to_string_lossy()),
+        shell_quote(&plugin.name()),
+    );
+    if let Some(key) = key {
+        load.push_str(&format!(" --key {}", shell_quote(&key.to_string())));
+    }
+    load
+}
+
+/// What every [`load_command`] for `plugin` contains, to find its hooks by.
+pub fn load_marker(plugin: &Plugins) -> String {
+    format!("load {}", shell_quote(&plugin.name()))
+}
+
+/// `command` as the argument of `run-shell`, which expands formats: a
+/// literal `#` is doubled.
+pub fn run_shell_arg(command: &str) -> String {
+    command.replace('#', "##")
+}
+
+/// The `set-hook` command running `command`, quoted for tmux.
+pub fn hook_command(command: &str) -> String {
+    format!("run-shell {}", tmux_quote(&run_shell_arg(command)))
+}
+
+/// Wraps `s` in double quotes for the tmux command parser.
+pub fn tmux_quote(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        if matches!(c, '"' | '\\' | '$') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Wraps `s` in single quotes for `sh`.
+pub fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// The command of a binding as `tmux list-keys -T table key` prints it, e.g.
+/// `run-shell "x"` out of `bind-key -r -T prefix C-s run-shell "x"`.
+pub fn bound_command(binding: &str) -> Option<&str> {
+    let mut rest = binding.trim().strip_prefix("bind-key")?;
+    let take = |rest: &mut &str| {
+        let trimmed = rest.trim_start();
+        let end = trimmed.find(char::is_whitespace).unwrap_or(trimmed.len());
+        let (word, tail) = trimmed.split_at(end);
+        *rest = tail;
+        word.to_string()
+    };
+    let mut word = take(&mut rest);
+    while word.starts_with('-') && word != "-T" {
+        word = take(&mut rest);
+    }
+    if word != "-T" {
+        return None;
+    }
+    take(&mut rest); // table
+    take(&mut rest); // key
+    Some(rest.trim()).filter(|c| !c.is_empty())
+}
+
+/// The hooks in `show_hooks`, as `tmux show-hooks -g` prints them, whose
+/// command contains `command`, e.g. `client-attached[1]`.
+pub fn hook_slots<'a>(show_hooks: &'a str, command: &str) -> Vec<&'a str> {
+    show_hooks
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .filter(|(slot, cmd)| slot.ends_with(']') && cmd.contains(command))
+        .map(|(slot, _)| slot)
+        .collect()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::report::PluginReport;
+
+    #[test]
+    fn parses_lazy_keys_and_bindings() {
+        let key: LazyKey = "C-s".parse().unwrap();
+        assert_eq!((key.table(), key.key()), ("prefix", "C-s"));
+        assert_eq!(
+            "root M-u".parse::<LazyKey>().unwrap().to_string(),
+            "root M-u"
+        );
+        assert!("a b c".parse::<LazyKey>().is_err());
+
+        assert_eq!(
+            bound_command("bind-key -r -T prefix C-s run-shell \"echo hi\"\n"),
+            Some("run-shell \"echo hi\"")
+        );
+        assert_eq!(
+            bound_command("bind-key    -T root M-x display-message \"a b\""),
+            Some("display-message \"a b\"")
+        );
+        assert_eq!(bound_command("unknown key: C-q"), None);
+    }
+
+    #[test]
+    fn quotes_for_sh_and_tmux() {
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+        assert_eq!(tmux_quote(r#"a "b" $c \"#), r#""a \"b\" \$c \\""#);
+        assert_eq!(run_shell_arg("#{pane_id} #"), "##{pane_id} ##");
+    }
+
+    #[test]
+    fn builds_load_commands_and_hooks() {
+        let plugin = Plugins::new("o".into(), "r".into(), None, None);
+        let key: LazyKey = "C-s".parse().unwrap();
+        let load = load_command(
+            Path::new("/bin/lazy-tmux"),
+            Path::new("/dots/it's #1.toml"),
+            &plugin,
+            Some(&key),
+        );
+        assert_eq!(
+            load,
+            r"'/bin/lazy-tmux' --config '/dots/it'\''s #1.toml' load 'o/r' --key 'prefix C-s'"
+        );
+
+        let load = load_command(
+            Path::new("/bin/lazy-tmux"),
+            Path::new("/dots/p.toml"),
+            &plugin,
+            None,
+        );
+        let hook = hook_command(&load);
+        assert_eq!(
+            hook,
+            r#"run-shell "'/bin/lazy-tmux' --config '/dots/p.toml' load 'o/r'""#
+        );
+        // what `disarm` looks for in `show-hooks` matches what `arm` set
+        let shown = format!("client-attached[0] {}\n", hook);
+        assert_eq!(
+            hook_slots(&shown, &load_marker(&plugin)),
+            ["client-attached[0]"]
+        );
+    }
+
+    #[test]
+    fn finds_hooks_running_a_command() {
+        let hooks = "client-attached[0] run-shell \"lazy-tmux load 'o/r'\"\n\
+                     client-attached[1] display-message hi\n\
+                     pane-died[3] run-shell \"lazy-tmux load 'o/r'\"\n\
+                     session-created\n";
+        assert_eq!(
+            hook_slots(hooks, "load 'o/r'"),
+            ["client-attached[0]", "pane-died[3]"]
+        );
+        assert!(hook_slots(hooks, "load 'o/s'").is_empty());
+    }
+
+    #[test]
+    fn sources_fresh_plugins_in_load_order() {
+        let input = "[[plugins]]\nowner = \"o\"\nrepo = \"a\"\nafter = [\"o/c\"]\n\
+                     [[plugins]]\nowner = \"o\"\nrepo = \"b\"\n\
+                     [[plugins]]\nowner = \"o\"\nrepo = \"c\"\n";
+        let config = ConfigFile::parse(input, Path::new("/dots/tmux/plugins.toml")).unwrap();
+        let outcomes = [
+            Outcome::Installed {
+                commit: "a".to_string(),
+            },
+            Outcome::AlreadyPresent,
+            Outcome::Installed {
+                commit: "c".to_string(),
+            },
+        ];
+        let report: OperationReport = config
+            .plugins()
+            .iter()
+            .zip(outcomes)
+            .map(|(p, o)| PluginReport::new(p, o))
+            .collect();
+        let repos: Vec<&str> = freshly_installed(&config, &report)
+            .iter()
+            .map(|p| p.repo())
+            .collect();
+        assert_eq!(repos, ["c", "a"]);
+    }
+
+    #[test]
+    fn only_executable_top_level_scripts() {
+        let dir = std::env::temp_dir().join(format!("lazy-tmux-source-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("tests")).unwrap();
+        for (file, mode) in [
+            ("b.tmux", 0o755),
+            ("a.tmux", 0o755),
+            ("plain.tmux", 0o644),
+            ("run.sh", 0o755),
+            ("tests/t.tmux", 0o755),
+        ] {
+            fs::write(dir.join(file), "").unwrap();
+            fs::set_permissions(dir.join(file), fs::Permissions::from_mode(mode)).unwrap();
+        }
+
+        let mut plugin = Plugins::default();
+        plugin.set_path(Some(dir.clone()));
+        assert_eq!(
+            entry_points(&plugin).unwrap(),
+            [dir.join("a.tmux"), dir.join("b.tmux")]
+        );
+
+        plugin.set_entry(vec!["tests/t.tmux".into(), "b.tmux".into()]);
+        assert_eq!(
+            entry_points(&plugin).unwrap(),
+            [dir.join("tests/t.tmux"), dir.join("b.tmux")]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,189 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::error::SpecError;
+use crate::git::GitRef;
+use crate::plugins::Plugins;
+
+/// A plugin written as `[host/]owner/repo[@ref]`, e.g. `tmux-plugins/tmux-yank`
+/// or `gitlab.com/foo/bar@tag:v2`.
+///
+/// A ref is a branch unless written `tag:<name>`, [`PluginSpec::resolve_ref`]
+/// asks the remote which one a bare ref is. Refs that look like commit SHAs
+/// are rejected, commits are pinned with `commit` in a plugin table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PluginSpec {
+    host: Option<String>,
+    owner: String,
+    repo: String,
+    branch: Option<String>,
+    tag: Option<String>,
+}
+
+impl PluginSpec {
+    /// getter method for host
+    #[inline]
+    pub fn host(&self) -> Option<&str> {
+        self.host.as_deref()
+    }
+
+    /// setter method for host
+    #[inline]
+    pub fn set_host(&mut self, host: Option<String>) {
+        self.host = host;
+    }
+
+    /// getter method for owner
+    #[inline]
+    pub fn owner(&self) -> &str {
+        &self.owner
+    }
+
+    /// getter method for repo
+    #[inline]
+    pub fn repo(&self) -> &str {
+        &self.repo
+    }
+
+    /// getter method for branch
+    #[inline]
+    pub fn branch(&self) -> Option<&str> {
+        self.branch.as_deref()
+    }
+
+    /// follows `branch` instead, dropping any tag
+    #[inline]
+    pub fn set_branch(&mut self, branch: Option<String>) {
+        if branch.is_some() {
+            self.tag = None;
+        }
+        self.branch = branch;
+    }
+
+    /// getter method for tag
+    #[inline]
+    pub fn tag(&self) -> Option<&str> {
+        self.tag.as_deref()
+    }
+
+    /// turns a bare `@ref` the remote only has as a tag into `@tag:`, when
+    /// the remote can't tell it stays a branch and clone checks it again
+    pub fn resolve_ref(&mut self) {
+        let Some(branch) = self.branch.clone() else {
+            return;
+        };
+        let plugin: Plugins = self.clone().into();
+        if let Ok(Some(GitRef::Tag(_))) = plugin.git().remote_ref(&branch) {
+            self.branch = None;
+            self.tag = Some(branch);
+        }
+    }
+}
+
+impl FromStr for PluginSpec {
+    type Err = SpecError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || SpecError::Invalid(s.to_string());
+        let (path, reference) = match s.trim().rsplit_once('@') {
+            Some((_, "")) => return Err(SpecError::EmptyRef(s.to_string())),
+            Some((path, reference)) => (path, Some(reference)),
+            None => (s.trim(), None),
+        };
+        let parts: Vec<&str> = path.split('/').collect();
+        if parts
+            .iter()
+            .any(|p| p.is_empty() || p.contains(char::is_whitespace))
+        {
+            return Err(invalid());
+        }
+        let (host, owner, repo) = match parts[..] {
+            [owner, repo] => (None, owner, repo),
+            [host, owner, repo] => (Some(host), owner, repo),
+            _ => return Err(invalid()),
+        };
+        let (branch, tag) = match reference {
+            Some(r) if looks_like_commit(r) => return Err(SpecError::Commit(s.to_string())),
+            Some(r) => match r.strip_prefix("tag:") {
+                Some("") => return Err(SpecError::EmptyRef(s.to_string())),
+                Some(tag) => (None, Some(tag.to_string())),
+                None => (Some(r.to_string()), None),
+            },
+            None => (None, None),
+        };
+        Ok(Self {
+            host: host.map(str::to_string),
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            branch,
+            tag,
+        })
+    }
+}
+
+impl Display for PluginSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(host) = &self.host {
+            write!(f, "{}/", host)?;
+        }
+        write!(f, "{}/{}", self.owner, self.repo)?;
+        match (&self.branch, &self.tag) {
+            (Some(branch), _) => write!(f, "@{}", branch),
+            (_, Some(tag)) => write!(f, "@tag:{}", tag),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl From<PluginSpec> for Plugins {
+    fn from(spec: PluginSpec) -> Self {
+        let mut plugin = Plugins::new(
+            spec.owner.into(),
+            spec.repo.into(),
+            spec.host.map(Into::into),
+            spec.branch.map(Into::into),
+        );
+        plugin.set_tag(spec.tag.map(Into::into));
+        plugin
+    }
+}
+
+/// 7 to 40 hex digits, an abbreviated or full SHA.
+fn looks_like_commit(reference: &str) -> bool {
+    (7..=40).contains(&reference.len()) && reference.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_host_owner_repo_and_ref() {
+        let spec: PluginSpec = "gitlab.com/foo/bar@tag:v2".parse().unwrap();
+        assert_eq!(spec.host(), Some("gitlab.com"));
+        assert_eq!((spec.owner(), spec.repo()), ("foo", "bar"));
+        assert_eq!((spec.branch(), spec.tag()), (None, Some("v2")));
+        assert_eq!(spec.to_string(), "gitlab.com/foo/bar@tag:v2");
+
+        let spec: PluginSpec = "tmux-plugins/tmux-yank@master".parse().unwrap();
+        assert_eq!((spec.host(), spec.branch()), (None, Some("master")));
+
+        // version-like names are branches unless marked as tags
+        for reference in ["2.x", "v1-maintenance", "v2"] {
+            let spec: PluginSpec = format!("o/r@{}", reference).parse().unwrap();
+            assert_eq!((spec.branch(), spec.tag()), (Some(reference), None));
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_specs() {
+        for spec in ["tmux-yank", "a/b/c/d", "o//r", "o/r@", "o/r x", "o/r@tag:"] {
+            assert!(spec.parse::<PluginSpec>().is_err(), "{}", spec);
+        }
+        assert_eq!(
+            "o/r@25cb91f".parse::<PluginSpec>(),
+            Err(SpecError::Commit("o/r@25cb91f".to_string()))
+        );
+    }
+}
@@ -0,0 +1,423 @@
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::git::GitRef;
+use crate::plugins::{ConfigFile, Plugins};
+
+/// What a configured plugin looks like on disk.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PluginStatus {
+    plugin: String,
+    installed: bool,
+    /// its `when` doesn't hold on this machine, so it's never installed here
+    skipped: bool,
+    /// set for local plugins
+    path: Option<PathBuf>,
+    #[serde(rename = "ref")]
+    reference: Option<String>,
+    branch: Option<String>,
+    commit: Option<String>,
+    dirty: bool,
+    shallow: bool,
+    /// unix timestamp of the last time HEAD moved
+    last_updated: Option<u64>,
+    /// pinned to a tag or commit, which is always checked out detached
+    #[serde(skip)]
+    pinned: bool,
+}
+
+impl PluginStatus {
+    /// inspects the plugin's dir
+    pub fn of(plugin: &Plugins) -> Self {
+        if let Some(path) = plugin.local_path() {
+            return Self {
+                plugin: plugin.name(),
+                installed: path.exists(),
+                skipped: false,
+                path: Some(path),
+                reference: None,
+                branch: None,
+                commit: None,
+                dirty: false,
+                shallow: false,
+                last_updated: None,
+                pinned: false,
+            };
+        }
+        let git = plugin.git();
+        let installed = git.dir().exists();
+        Self {
+            plugin: plugin.name(),
+            installed,
+            skipped: false,
+            path: None,
+            reference: plugin.git_ref().map(|r| r.to_string()),
+            branch: installed.then(|| git.branch()).flatten(),
+            commit: installed.then(|| git.head()).flatten(),
+            dirty: installed && git.is_dirty(),
+            shallow: installed && git.is_shallow(),
+            last_updated: installed
+                .then(|| git.last_updated())
+                .flatten()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs()),
+            pinned: matches!(plugin.git_ref(), Some(GitRef::Tag(_) | GitRef::Commit(_))),
+        }
+    }
+
+    /// `owner/repo` of the plugin
+    #[inline]
+    pub fn plugin(&self) -> &str {
+        &self.plugin
+    }
+
+    /// whether the plugin dir exists
+    #[inline]
+    pub fn installed(&self) -> bool {
+        self.installed
+    }
+
+    /// full SHA of the checked out commit
+    #[inline]
+    pub fn commit(&self) -> Option<&str> {
+        self.commit.as_deref()
+    }
+
+    /// whether its `when` doesn't hold on this machine
+    #[inline]
+    pub fn skipped(&self) -> bool {
+        self.skipped
+    }
+
+    fn ref_column(&self) -> String {
+        match (&self.branch, &self.reference) {
+            (Some(branch), _) => branch.clone(),
+            (None, Some(pin)) if self.pinned => pin.clone(),
+            // not on the configured branch, e.g. after a manual checkout
+            (None, Some(configured)) => format!("(detached) {}", configured),
+            (None, None) if self.installed => "(detached)".to_string(),
+            _ => "-".to_string(),
+        }
+    }
+}
+
+/// How an installed plugin compares to its upstream.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Upstream {
+    /// nothing new upstream
+    UpToDate,
+    /// `count` new commits upstream, the newest being `sha` `subject`
+    Behind {
+        count: usize,
+        sha: String,
+        subject: String,
+    },
+    /// pinned to a tag or commit, never updated
+    Pinned { pin: String },
+    /// not installed, nothing to compare
+    Missing,
+    /// a local plugin, not tracked by lazy-tmux
+    Local,
+    /// the remote couldn't be fetched
+    Failed { reason: String },
+}
+
+/// The upstream state of one plugin, see [`UpstreamStatus::check`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UpstreamStatus {
+    plugin: String,
+    #[serde(flatten)]
+    upstream: Upstream,
+}
+
+impl UpstreamStatus {
+    /// fetches the plugin's upstream branch and compares it with HEAD
+    pub fn check(plugin: &Plugins) -> Self {
+        let git = plugin.git();
+        let upstream = match plugin.git_ref() {
+            _ if plugin.is_local() => Upstream::Local,
+            _ if !git.dir().exists() => Upstream::Missing,
+            Some(pin @ (GitRef::Tag(_) | GitRef::Commit(_))) => Upstream::Pinned {
+                pin: pin.to_string(),
+            },
+            _ => match git.behind() {
+                Ok((0, _)) => Upstream::UpToDate,
+                Ok((count, newest)) => {
+                    let (sha, subject) = newest.unwrap_or_default();
+                    Upstream::Behind {
+                        count,
+                        sha,
+                        subject,
+                    }
+                }
+                Err(err) => Upstream::Failed {
+                    reason: err.to_string(),
+                },
+            },
+        };
+        Self {
+            plugin: plugin.name(),
+            upstream,
+        }
+    }
+
+    /// `owner/repo` of the plugin
+    #[inline]
+    pub fn plugin(&self) -> &str {
+        &self.plugin
+    }
+
+    /// getter method for upstream
+    #[inline]
+    pub fn upstream(&self) -> &Upstream {
+        &self.upstream
+    }
+}
+
+/// Renders upstream statuses as an aligned table.
+pub fn render_outdated(statuses: &[UpstreamStatus]) -> String {
+    let width = statuses
+        .iter()
+        .map(|s| s.plugin.len())
+        .max()
+        .unwrap_or_default()
+        .max("PLUGIN".len());
+    let mut out = String::new();
+    let _ = writeln!(out, "{:<width$}  {:<6}  LATEST", "PLUGIN", "BEHIND");
+    for status in statuses {
+        let (behind, latest) = match &status.upstream {
+            Upstream::UpToDate => ("0".to_string(), "up to date".to_string()),
+            Upstream::Behind {
+                count,
+                sha,
+                subject,
+            } => (count.to_string(), format!("{} {}", sha, subject)),
+            Upstream::Pinned { pin } => ("-".to_string(), format!("pinned to {}", pin)),
+            Upstream::Missing => ("-".to_string(), "not installed".to_string()),
+            Upstream::Local => ("-".to_string(), "local".to_string()),
+            Upstream::Failed { reason } => {
+                // the first line of git's stderr, like the install report
+                let reason = reason.lines().next().unwrap_or_default();
+                ("-".to_string(), format!("failed: {}", reason))
+            }
+        };
+        let line = format!("{:<width$}  {:<6}  {}", status.plugin, behind, latest);
+        let _ = writeln!(out, "{}", line.trim_end());
+    }
+    out
+}
+
+/// Status of every plugin in `config`, in config order, followed by the ones
+/// skipped on this machine.
+pub fn statuses(config: &ConfigFile) -> Vec<PluginStatus> {
+    let skipped = config.skipped().iter().map(|plugin| PluginStatus {
+        skipped: true,
+        ..PluginStatus::of(plugin)
+    });
+    config
+        .plugins()
+        .iter()
+        .map(PluginStatus::of)
+        .chain(skipped)
+        .collect()
+}
+
+/// Renders statuses as an aligned table.
+pub fn render(statuses: &[PluginStatus]) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let header = [
+        "PLUGIN", "STATE", "REF", "COMMIT", "TREE", "CLONE", "UPDATED",
+    ];
+    let rows: Vec<[String; 7]> = statuses
+        .iter()
+        .map(|s| {
+            let dash = || "-".to_string();
+            if s.skipped {
+                return [
+                    s.plugin.clone(),
+                    "skipped".to_string(),
+                    s.reference.clone().unwrap_or_else(dash),
+                    dash(),
+                    dash(),
+                    dash(),
+                    dash(),
+                ];
+            }
+            if let Some(path) = s.path.as_ref().filter(|_| s.installed) {
+                return [
+                    s.plugin.clone(),
+                    "local".to_string(),
+                    path.display().to_string(),
+                    dash(),
+                    dash(),
+                    dash(),
+                    dash(),
+                ];
+            }
+            if !s.installed {
+                return [
+                    s.plugin.clone(),
+                    "missing".to_string(),
+                    s.reference.clone().unwrap_or_else(dash),
+                    dash(),
+                    dash(),
+                    dash(),
+                    dash(),
+                ];
+            }
+            [
+                s.plugin.clone(),
+                "installed".to_string(),
+                s.ref_column(),
+                s.commit
+                    .as_deref()
+                    .map(|c| c.get(..7).unwrap_or(c).to_string())
+                    .unwrap_or_else(dash),
+                if s.dirty { "dirty" } else { "clean" }.to_string(),
+                if s.shallow { "shallow" } else { "full" }.to_string(),
+                s.last_updated
+                    .map(|t| ago(now.saturating_sub(t)))
+                    .unwrap_or_else(dash),
+            ]
+        })
+        .collect();
+
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (w, cell) in widths.iter_mut().zip(row) {
+            *w = (*w).max(cell.chars().count());
+        }
+    }
+    let mut out = String::new();
+    let line = |out: &mut String, cells: &[&str]| {
+        let cells: Vec<String> = cells
+            .iter()
+            .zip(widths)
+            .map(|(c, w)| format!("{:<w$}", c))
+            .collect();
+        let _ = writeln!(out, "{}", cells.join("  ").trim_end());
+    };
+    line(&mut out, &header);
+    for row in &rows {
+        line(&mut out, &row.each_ref().map(String::as_str));
+    }
+    out
+}
+
+/// Formats an age in seconds the way `git log --relative-date` does, roughly.
+fn ago(secs: u64) -> String {
+    let (n, unit) = match secs {
+        0..60 => return "just now".to_string(),
+        60..3_600 => (secs / 60, "minute"),
+        3_600..86_400 => (secs / 3_600, "hour"),
+        86_400..2_592_000 => (secs / 86_400, "day"),
+        2_592_000..31_536_000 => (secs / 2_592_000, "month"),
+        _ => (secs / 31_536_000, "year"),
+    };
+    format!("{} {}{} ago", n, unit, if n == 1 { "" } else { "s" })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_ages() {
+        assert_eq!(ago(5), "just now");
+        assert_eq!(ago(60), "1 minute ago");
+        assert_eq!(ago(3 * 86_400), "3 days ago");
+    }
+
+    #[test]
+    fn renders_outdated_table() {
+        let statuses = [
+            UpstreamStatus {
+                plugin: "o/r".to_string(),
+                upstream: Upstream::Behind {
+                    count: 3,
+                    sha: "526974c".to_string(),
+                    subject: "change 3".to_string(),
+                },
+            },
+            UpstreamStatus {
+                plugin: "tmux-plugins/tmux-yank".to_string(),
+                upstream: Upstream::Pinned {
+                    pin: "tag v2.3.0".to_string(),
+                },
+            },
+            UpstreamStatus {
+                plugin: "o/gone".to_string(),
+                upstream: Upstream::Failed {
+                    reason: "git fetch failed: fatal: no repo\nfatal: Could not read".to_string(),
+                },
+            },
+        ];
+        assert_eq!(
+            render_outdated(&statuses),
+            "PLUGIN                  BEHIND  LATEST\n\
+             o/r                     3       526974c change 3\n\
+             tmux-plugins/tmux-yank  -       pinned to tag v2.3.0\n\
+             o/gone                  -       failed: git fetch failed: fatal: no repo\n"
+        );
+    }
+
+    #[test]
+    fn renders_missing_plugin() {
+        let status = PluginStatus {
+            plugin: "tmux-plugins/tmux-sensible".to_string(),
+            installed: false,
+            skipped: false,
+            path: None,
+            reference: Some("tag v3.0.0".to_string()),
+            branch: None,
+            commit: None,
+            dirty: false,
+            shallow: false,
+            last_updated: None,
+            pinned: true,
+        };
+        assert_eq!(
+            render(&[status]),
+            "PLUGIN                      STATE    REF         COMMIT  TREE  CLONE  UPDATED\n\
+             tmux-plugins/tmux-sensible  missing  tag v3.0.0  -       -     -      -\n"
+        );
+    }
+
+    #[test]
+    fn renders_skipped_and_detached_plugins() {
+        let detached = PluginStatus {
+            plugin: "o/r".to_string(),
+            installed: true,
+            skipped: false,
+            path: None,
+            reference: Some("branch v1".to_string()),
+            branch: None,
+            commit: Some("526974c3d1".to_string()),
+            dirty: false,
+            shallow: true,
+            last_updated: None,
+            pinned: false,
+        };
+        let skipped = PluginStatus {
+            plugin: "o/battery".to_string(),
+            installed: false,
+            skipped: true,
+            reference: None,
+            commit: None,
+            ..detached.clone()
+        };
+        assert_eq!(
+            render(&[detached, skipped]),
+            "PLUGIN     STATE      REF                   COMMIT   TREE   CLONE    UPDATED\n\
+             o/r        installed  (detached) branch v1  526974c  clean  shallow  -\n\
+             o/battery  skipped    -                     -        -      -        -\n"
+        );
+    }
+}
@@ -0,0 +1,329 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::process::Command;
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
+
+use serde::Deserialize;
+
+use crate::plugins::one_or_many;
+
+/// The `when` table of a plugin: conditions on the machine that must all hold
+/// for the plugin to be installed and sourced there.
+///
+/// Each list matches if any of its entries does, an empty one always matches.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct When {
+    /// globs on the hostname, e.g. `work-*`
+    #[serde(default, deserialize_with = "one_or_many")]
+    hostname: Vec<Arc<str>>,
+    /// `NAME` for a variable that is set, `NAME=value` for one set to `value`
+    #[serde(default, deserialize_with = "one_or_many")]
+    env: Vec<Arc<str>>,
+    /// `linux`, `macos`, `freebsd`, ...
+    #[serde(default, deserialize_with = "one_or_many")]
+    os: Vec<Arc<str>>,
+    /// constraints on the tmux version, e.g. `>=3.2` or `>=3.2, <3.4`
+    #[serde(default)]
+    tmux: Option<Arc<str>>,
+}
+
+impl When {
+    /// getter method for tmux
+    #[inline]
+    pub fn tmux(&self) -> Option<&str> {
+        self.tmux.as_deref()
+    }
+
+    /// setter method for tmux
+    #[inline]
+    pub fn set_tmux(&mut self, tmux: Option<Arc<str>>) {
+        self.tmux = tmux;
+    }
+
+    /// setter method for hostname
+    #[inline]
+    pub fn set_hostname(&mut self, hostname: Vec<Arc<str>>) {
+        self.hostname = hostname;
+    }
+
+    /// setter method for env
+    #[inline]
+    pub fn set_env(&mut self, env: Vec<Arc<str>>) {
+        self.env = env;
+    }
+
+    /// setter method for os
+    #[inline]
+    pub fn set_os(&mut self, os: Vec<Arc<str>>) {
+        self.os = os;
+    }
+
+    /// checks every condition can be evaluated
+    ///
+    /// # Errors
+    ///
+    /// fails with a message if the `tmux` constraint or an `env` entry is
+    /// malformed
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(tmux) = &self.tmux {
+            tmux.parse::<VersionReq>()?;
+        }
+        if let Some(env) = self.env.iter().find(|e| e.split('=').next() == Some("")) {
+            return Err(format!("invalid env condition `{}`", env));
+        }
+        Ok(())
+    }
+
+    /// whether `host` meets every condition, malformed ones never match
+    pub fn matches(&self, host: &Host) -> bool {
+        let any = |list: &[Arc<str>], f: &dyn Fn(&str) -> bool| {
+            list.is_empty() || list.iter().any(|e| f(e))
+        };
+        any(&self.hostname, &|glob| glob_match(glob, &host.hostname))
+            && any(&self.os, &|os| os == host.os)
+            && any(&self.env, &|cond| match cond.split_once('=') {
+                Some((name, value)) => host.env.get(name).is_some_and(|v| v == value),
+                None => host.env.contains_key(cond),
+            })
+            && self.tmux.as_ref().is_none_or(|req| {
+                req.parse::<VersionReq>()
+                    .is_ok_and(|req| host.tmux().is_some_and(|v| req.matches(v)))
+            })
+    }
+}
+
+/// What `when` conditions are checked against.
+#[derive(Debug, Default)]
+pub struct Host {
+    hostname: String,
+    os: String,
+    env: HashMap<String, String>,
+    /// read on first use, most configs don't ask for it
+    tmux: OnceLock<Option<TmuxVersion>>,
+}
+
+impl Host {
+    /// the machine and environment lazy-tmux runs in
+    pub fn current() -> Self {
+        Self {
+            hostname: hostname(),
+            os: std::env::consts::OS.to_string(),
+            // `vars` panics on a variable that isn't UTF-8, no condition can name it
+            env: std::env::vars_os()
+                .filter_map(|(k, v)| Some((k.into_string().ok()?, v.into_string().ok()?)))
+                .collect(),
+            tmux: OnceLock::new(),
+        }
+    }
+
+    /// a host with the given facts instead of this machine's
+    pub fn new(hostname: &str, os: &str, env: &[(&str, &str)], tmux: Option<TmuxVersion>) -> Self {
+        Self {
+            hostname: hostname.to_string(),
+            os: os.to_string(),
+            env: env
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            tmux: OnceLock::from(tmux),
+        }
+    }
+
+    /// the version `tmux -V` reports, if tmux can be run
+    pub fn tmux(&self) -> Option<TmuxVersion> {
+        *self.tmux.get_or_init(|| {
+            let output = Command::new("tmux").arg("-V").output().ok()?;
+            String::from_utf8_lossy(&output.stdout)
+                .split_whitespace()
+                .last()?
+                .parse()
+                .ok()
+        })
+    }
+}
+
+/// The name of this machine, empty if it can't be found.
+fn hostname() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .ok()
+        .or_else(|| {
+            let output = Command::new("hostname").output().ok()?;
+            Some(String::from_utf8_lossy(&output.stdout).into_owned())
+        })
+        .map(|h| h.trim().to_string())
+        .unwrap_or_default()
+}
+
+/// Matches `text` against a glob where `*` is any run of characters and `?`
+/// any single one.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let (pattern, text): (Vec<char>, Vec<char>) =
+        (pattern.chars().collect(), text.chars().collect());
+    let (mut p, mut t) = (0, 0);
+    // where the last `*` was and the text position it's matched up to
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((sp, st)) => {
+                    star = Some((sp, st + 1));
+                    p = sp + 1;
+                    t = st + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// A tmux version such as `3.3a`, `next-3.5` reads as `3.5`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TmuxVersion {
+    major: u32,
+    minor: u32,
+    /// the letter of patch releases, `a` is 1
+    patch: u32,
+}
+
+impl FromStr for TmuxVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid tmux version `{}`", s);
+        let version = s.trim().trim_start_matches("next-");
+        let (major, rest) = version.split_once('.').ok_or_else(invalid)?;
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let (minor, suffix) = rest.split_at(digits);
+        let patch = match suffix.chars().next() {
+            Some(c) if c.is_ascii_lowercase() => u32::from(c) - u32::from('a') + 1,
+            _ => 0,
+        };
+        Ok(Self {
+            major: major.parse().map_err(|_| invalid())?,
+            minor: minor.parse().map_err(|_| invalid())?,
+            patch,
+        })
+    }
+}
+
+impl Display for TmuxVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)?;
+        match self.patch.checked_sub(1) {
+            Some(letter) => write!(f, "{}", char::from(b'a' + letter as u8)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Comma separated constraints on a [`TmuxVersion`], all of which must hold,
+/// e.g. `>=3.2, <3.4`. A bare version means `=`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VersionReq(Vec<(Vec<Ordering>, TmuxVersion)>);
+
+impl VersionReq {
+    /// whether `version` meets every constraint
+    pub fn matches(&self, version: TmuxVersion) -> bool {
+        self.0
+            .iter()
+            .all(|(allowed, wanted)| allowed.contains(&version.cmp(wanted)))
+    }
+}
+
+impl FromStr for VersionReq {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use Ordering::{Equal, Greater, Less};
+        let mut constraints = Vec::new();
+        for part in s.split(',') {
+            let part = part.trim();
+            let (allowed, version) = [
+                (">=", vec![Greater, Equal]),
+                ("<=", vec![Less, Equal]),
+                (">", vec![Greater]),
+                ("<", vec![Less]),
+                ("=", vec![Equal]),
+            ]
+            .into_iter()
+            .find_map(|(op, allowed)| part.strip_prefix(op).map(|v| (allowed, v)))
+            .unwrap_or((vec![Equal], part));
+            let version = version
+                .parse()
+                .map_err(|_| format!("invalid tmux version constraint `{}`", s))?;
+            constraints.push((allowed, version));
+        }
+        Ok(Self(constraints))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn globs() {
+        assert!(glob_match("work-*", "work-laptop"));
+        assert!(glob_match("*.corp", "build.corp"));
+        assert!(glob_match("box-?", "box-1"));
+        assert!(glob_match("a*b*c", "axxbyyc"));
+        assert!(!glob_match("work-*", "home-laptop"));
+        assert!(!glob_match("box-?", "box-10"));
+    }
+
+    #[test]
+    fn tmux_versions() {
+        let v = |s: &str| s.parse::<TmuxVersion>().unwrap();
+        assert!(v("3.3a") > v("3.3"));
+        assert!(v("next-3.5") > v("3.4"));
+        assert_eq!(v("3.3a").to_string(), "3.3a");
+
+        let req: VersionReq = ">=3.2, <3.4".parse().unwrap();
+        assert!(req.matches(v("3.3a")));
+        assert!(!req.matches(v("3.1c")));
+        assert!(!req.matches(v("3.4")));
+        assert!("3.2".parse::<VersionReq>().unwrap().matches(v("3.2")));
+        assert!(">= three".parse::<VersionReq>().is_err());
+    }
+
+    #[test]
+    fn all_conditions_must_hold() {
+        let host = Host::new(
+            "work-laptop",
+            "linux",
+            &[("DISPLAY", ":0"), ("TERM", "xterm-kitty")],
+            "3.3a".parse().ok(),
+        );
+        let mut when = When::default();
+        assert!(when.matches(&host));
+
+        when.set_hostname(vec!["home-*".into(), "work-*".into()]);
+        when.set_os(vec!["linux".into(), "macos".into()]);
+        when.set_env(vec!["DISPLAY".into(), "TERM=xterm-kitty".into()]);
+        when.set_tmux(Some(">=3.2".into()));
+        assert!(when.matches(&host));
+
+        when.set_env(vec!["SSH_TTY".into()]);
+        assert!(!when.matches(&host));
+        when.set_env(vec!["TERM=screen".into()]);
+        assert!(!when.matches(&host));
+        when.set_env(Vec::new());
+        when.set_tmux(Some(">=3.4".into()));
+        assert!(!when.matches(&host));
+    }
+}
@@ -0,0 +1,133 @@
+//! Runs the binary, each run gets its own environment so nothing touches the
+//! real config.
+
+#![allow(clippy::unwrap_used)]
+
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt as _;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A temp dir with `env.toml`, `cli.toml` and the default `tmux/plugins.toml`
+/// under it as `XDG_CONFIG_HOME`, each naming one plugin.
+fn configs(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("lazy-tmux-cli-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("tmux")).unwrap();
+    for (owner, file) in [
+        ("env", "env.toml"),
+        ("cli", "cli.toml"),
+        ("default", "tmux/plugins.toml"),
+    ] {
+        let input = format!("[[plugins]]\nowner = \"{}\"\nrepo = \"r\"\n", owner);
+        std::fs::write(dir.join(file), input).unwrap();
+    }
+    dir
+}
+
+/// runs `lazy-tmux` with `XDG_CONFIG_HOME` set to `dir` and the variables in
+/// `env`, and returns stdout
+fn run(dir: &Path, env: &[(&str, &str)], args: &[&str]) -> String {
+    let out = Command::new(env!("CARGO_BIN_EXE_lazy-tmux"))
+        .args(args)
+        .env("XDG_CONFIG_HOME", dir)
+        .env_remove("LAZY_TMUX_CONFIG")
+        .envs(env.iter().copied())
+        .output()
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "lazy-tmux {:?}: {}",
+        args,
+        String::from_utf8_lossy(&out.stderr)
+    );
+    String::from_utf8_lossy(&out.stdout).into_owned()
+}
+
+#[test]
+fn config_env_is_the_fallback() {
+    let dir = configs("env");
+    let env_config = dir.join("env.toml");
+    let env = [("LAZY_TMUX_CONFIG", env_config.to_str().unwrap())];
+    let cli = dir.join("cli.toml");
+
+    let out = run(&dir, &env, &["list"]);
+    assert!(out.contains("env/r") && !out.contains("cli/r"), "{}", out);
+
+    // `-c` wins over the environment, before or after the subcommand
+    for args in [
+        ["-c", cli.to_str().unwrap(), "list"],
+        ["list", "-c", cli.to_str().unwrap()],
+    ] {
+        let out = run(&dir, &env, &args);
+        assert!(out.contains("cli/r") && !out.contains("env/r"), "{}", out);
+    }
+
+    // an empty variable is the same as an unset one
+    let out = run(&dir, &[("LAZY_TMUX_CONFIG", "")], &["list"]);
+    assert!(
+        out.contains("default/r") && !out.contains("env/r"),
+        "{}",
+        out
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn add_writes_a_ref_naming_a_tag_as_a_tag() {
+    let dir = configs("add");
+    let repo = dir.join("remote/o/r.git");
+    std::fs::create_dir_all(&repo).unwrap();
+    for args in [
+        &["init", "-q", "-b", "main"][..],
+        &["commit", "-q", "--allow-empty", "-m", "c1"],
+        &["tag", "v1"],
+    ] {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(&repo)
+            .args(args)
+            .envs([("GIT_AUTHOR_NAME", "t"), ("GIT_AUTHOR_EMAIL", "t@t")])
+            .envs([("GIT_COMMITTER_NAME", "t"), ("GIT_COMMITTER_EMAIL", "t@t")])
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+    // https://example.test/o/r.git is the repo above
+    let rewrite = format!("url.file://{}/.insteadOf", dir.join("remote").display());
+    let env = [
+        ("GIT_CONFIG_COUNT", "1"),
+        ("GIT_CONFIG_KEY_0", rewrite.as_str()),
+        ("GIT_CONFIG_VALUE_0", "https://example.test/"),
+    ];
+    let config = dir.join("cli.toml");
+    let config = config.to_str().unwrap();
+
+    run(&dir, &env, &["add", "example.test/o/r@v1", "-c", config]);
+    let written = std::fs::read_to_string(config).unwrap();
+    assert!(written.contains("tag = \"v1\""), "{}", written);
+
+    run(&dir, &env, &["remove", "o/r", "-c", config]);
+    run(&dir, &env, &["add", "example.test/o/r@main", "-c", config]);
+    let written = std::fs::read_to_string(config).unwrap();
+    assert!(written.contains("branch = \"main\""), "{}", written);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn variables_that_arent_utf8_are_ignored() {
+    let dir = configs("env-bytes");
+    let out = Command::new(env!("CARGO_BIN_EXE_lazy-tmux"))
+        .args(["list", "-c"])
+        .arg(dir.join("cli.toml"))
+        .env("XDG_CONFIG_HOME", &dir)
+        .env("LAZY_TMUX_BAD", OsStr::from_bytes(b"\xff"))
+        .output()
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
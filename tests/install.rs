@@ -1,13 +1,286 @@
-use lazy_tmux::plugins::ConfigFile;
+//! Installs and updates plugins from local bare repos, with
+//! `XDG_CONFIG_HOME` pointing at a temp dir so nothing touches the real
+//! plugin dir.
 
-#[test]
-fn test_name() {
-   /* let conf = ConfigFile::get_plugins().unwrap();
-   
-    for i in conf {
-        i.install().unwrap();
+#![allow(clippy::unwrap_used)]
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Once;
+
+use lazy_tmux::error::{GitError, PluginError};
+use lazy_tmux::lock::LockFile;
+use lazy_tmux::plugins::{ConfigFile, Plugins};
+use lazy_tmux::report::Outcome;
+use lazy_tmux::{install_plugins, restore_plugins, update_plugins, write_lockfile};
+
+/// Temp dir shared by every test in this file, each test uses its own owner.
+fn root() -> PathBuf {
+    static INIT: Once = Once::new();
+    let root = std::env::temp_dir().join(format!("lazy-tmux-it-{}", std::process::id()));
+    INIT.call_once(|| {
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        // SAFETY: set once, before any test reads the environment
+        unsafe { std::env::set_var("XDG_CONFIG_HOME", root.join("config")) };
+    });
+    root
+}
+
+fn git(dir: &Path, args: &[&str]) -> String {
+    let out = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .envs([
+            ("GIT_AUTHOR_NAME", "t"),
+            ("GIT_AUTHOR_EMAIL", "t@t"),
+            ("GIT_COMMITTER_NAME", "t"),
+            ("GIT_COMMITTER_EMAIL", "t@t"),
+        ])
+        .output()
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "git {:?}: {}",
+        args,
+        String::from_utf8_lossy(&out.stderr)
+    );
+    String::from_utf8_lossy(&out.stdout).trim().to_string()
+}
+
+/// A bare repo with `c1` (tag `v1`) and `c2` (tag `v2`) on `main`, and `d1`
+/// on `dev`, plus a work tree to push more commits from.
+struct Remote {
+    work: PathBuf,
+    url: String,
+}
+
+impl Remote {
+    fn new(name: &str) -> Self {
+        let dir = root().join(name);
+        let work = dir.join("work");
+        std::fs::create_dir_all(&work).unwrap();
+        git(&work, &["init", "-q", "-b", "main"]);
+        for (message, tag) in [("c1", "v1"), ("c2", "v2")] {
+            git(&work, &["commit", "-q", "--allow-empty", "-m", message]);
+            git(&work, &["tag", tag]);
+        }
+        git(&work, &["checkout", "-q", "-b", "dev"]);
+        git(&work, &["commit", "-q", "--allow-empty", "-m", "d1"]);
+        git(&work, &["checkout", "-q", "main"]);
+
+        let bare = dir.join("remote.git");
+        git(&dir, &["clone", "-q", "--bare", "work", "remote.git"]);
+        git(&work, &["remote", "add", "origin", &bare.to_string_lossy()]);
+        Self {
+            work,
+            url: format!("file://{}", bare.display()),
+        }
     }
-    
-    assert!(dirs::data_dir().unwrap().join("tmux/plugins/abhinadh-s/lazy.tmux").exists())
-*/
+
+    /// pushes a new commit onto `branch` and returns its SHA
+    fn commit(&self, branch: &str, message: &str) -> String {
+        git(&self.work, &["checkout", "-q", branch]);
+        git(
+            &self.work,
+            &["commit", "-q", "--allow-empty", "-m", message],
+        );
+        git(&self.work, &["push", "-q", "origin", branch]);
+        self.sha(branch)
+    }
+
+    fn sha(&self, rev: &str) -> String {
+        git(&self.work, &["rev-parse", &format!("{}^{{commit}}", rev)])
+    }
+
+    fn plugin(&self, owner: &str) -> Plugins {
+        let mut plugin = Plugins::new(owner.into(), "r".into(), None, None);
+        plugin.set_url(Some(self.url.as_str().into()));
+        plugin
+    }
+}
+
+#[test]
+fn clones_the_configured_branch() {
+    let remote = Remote::new("branch");
+    let mut plugin = remote.plugin("branch");
+    plugin.set_branch(Some("dev".into()));
+    plugin.install().unwrap();
+    assert_eq!(plugin.git().branch().as_deref(), Some("dev"));
+    assert_eq!(plugin.head(), Some(remote.sha("dev")));
+}
+
+#[test]
+fn update_follows_a_changed_branch() {
+    let remote = Remote::new("switch");
+    let mut plugin = remote.plugin("switch");
+    plugin.install().unwrap();
+    assert_eq!(plugin.git().branch().as_deref(), Some("main"));
+
+    plugin.set_branch(Some("dev".into()));
+    plugin.update().unwrap();
+    assert_eq!(plugin.git().branch().as_deref(), Some("dev"));
+    assert_eq!(plugin.head(), Some(remote.sha("dev")));
+
+    let newest = remote.commit("dev", "d2");
+    let outcome = Outcome::from_head_change(&plugin, || plugin.update());
+    assert!(matches!(outcome, Outcome::Updated { commits, .. } if commits.len() == 1));
+    assert_eq!(plugin.head(), Some(newest));
+
+    plugin.set_branch(Some("main".into()));
+    let outcome = Outcome::from_head_change(&plugin, || plugin.update());
+    assert!(matches!(outcome, Outcome::CheckedOut { .. }));
+    assert_eq!(plugin.head(), Some(remote.sha("main")));
+}
+
+#[test]
+fn rejects_a_branch_that_is_a_tag() {
+    let remote = Remote::new("not-branch");
+    let mut plugin = remote.plugin("not-branch");
+    plugin.set_branch(Some("v1".into()));
+    let err = plugin.install().unwrap_err();
+    assert!(
+        matches!(&err, PluginError::Git { source: GitError::BranchIsTag(b), .. } if b == "v1"),
+        "{}",
+        err
+    );
+    assert!(!plugin.dir().exists());
+
+    plugin.set_branch(Some("nope".into()));
+    let err = plugin.install().unwrap_err();
+    assert!(matches!(
+        err,
+        PluginError::Git {
+            source: GitError::NoSuchBranch(_),
+            ..
+        }
+    ));
+
+    plugin.set_branch(None);
+    plugin.install().unwrap();
+    plugin.set_branch(Some("v1".into()));
+    let err = plugin.update().unwrap_err();
+    assert!(matches!(
+        err,
+        PluginError::Git {
+            source: GitError::BranchIsTag(_),
+            ..
+        }
+    ));
+    assert_eq!(plugin.git().branch().as_deref(), Some("main"));
+}
+
+#[test]
+fn pins_hold_until_changed() {
+    let remote = Remote::new("pins");
+    let mut plugin = remote.plugin("pins");
+    plugin.set_tag(Some("v1".into()));
+    plugin.install().unwrap();
+    assert_eq!(plugin.head(), Some(remote.sha("v1")));
+
+    remote.commit("main", "c3");
+    plugin.update().unwrap();
+    assert_eq!(plugin.head(), Some(remote.sha("v1")));
+
+    plugin.set_tag(Some("v2".into()));
+    plugin.update().unwrap();
+    assert_eq!(plugin.head(), Some(remote.sha("v2")));
+
+    // going back to an older pin brings in no new commits
+    let v1 = remote.sha("v1");
+    plugin.set_tag(None);
+    plugin.set_commit(Some(v1[..7].into()));
+    let outcome = Outcome::from_head_change(&plugin, || plugin.update());
+    assert_eq!(
+        outcome,
+        Outcome::CheckedOut {
+            from: remote.sha("v2"),
+            to: v1.clone()
+        }
+    );
+
+    let mut pinned = remote.plugin("pins-commit");
+    pinned.set_commit(Some(v1[..7].into()));
+    pinned.install().unwrap();
+    assert_eq!(pinned.head(), Some(v1));
+}
+
+#[test]
+fn counts_commits_behind_and_logs_them() {
+    let remote = Remote::new("behind");
+    let plugin = remote.plugin("behind");
+    plugin.install().unwrap();
+    let old = plugin.head().unwrap();
+    assert_eq!(plugin.git().behind().unwrap().0, 0);
+
+    remote.commit("main", "c3");
+    remote.commit("main", "c4");
+    let (count, newest) = plugin.git().behind().unwrap();
+    assert_eq!(count, 2);
+    assert_eq!(newest.unwrap().1, "c4");
+    // `behind` only fetches
+    assert_eq!(plugin.head().as_deref(), Some(old.as_str()));
+
+    plugin.update().unwrap();
+    let subjects: Vec<String> = plugin
+        .log(&old, &plugin.head().unwrap())
+        .into_iter()
+        .map(|(_, subject)| subject)
+        .collect();
+    assert_eq!(subjects, ["c4", "c3"]);
+}
+
+#[test]
+fn restores_locked_commits() {
+    let remote = Remote::new("locked");
+    let input = format!(
+        "[[plugins]]\nowner = \"locked\"\nrepo = \"r\"\nurl = \"{}\"\n",
+        remote.url
+    );
+    let config = ConfigFile::parse(&input, &root().join("locked.toml")).unwrap();
+    assert!(!install_plugins(&config).has_failures());
+    let lock = LockFile::from_installed(&config);
+    let locked = remote.sha("main");
+    assert_eq!(lock.get(&config.plugins()[0]).unwrap().commit(), locked);
+
+    let newest = remote.commit("main", "c3");
+    assert!(!update_plugins(&config).has_failures());
+    assert_eq!(config.plugins()[0].head(), Some(newest));
+
+    assert!(!restore_plugins(&config, &lock).has_failures());
+    assert_eq!(config.plugins()[0].head(), Some(locked));
+}
+
+#[test]
+fn keeps_locked_entries_of_plugins_that_fail_to_clone() {
+    let remote = Remote::new("gone");
+    let input = format!(
+        "[[plugins]]\nowner = \"gone\"\nrepo = \"r\"\nurl = \"{}\"\n\
+         [[plugins]]\nowner = \"gone\"\nrepo = \"missing\"\nurl = \"file:///nonexistent/r.git\"\n",
+        remote.url
+    );
+    let config = ConfigFile::parse(&input, &root().join("gone.toml")).unwrap();
+    let path = LockFile::path(&config);
+    let locked = "25cb91f42d020f675bb0a2ce3fbd3a5d96119efa";
+    std::fs::write(
+        &path,
+        "[[plugins]]\nowner = \"gone\"\nrepo = \"missing\"\n\
+         url = \"file:///nonexistent/r.git\"\ncommit = \"25cb91f42d020f675bb0a2ce3fbd3a5d96119efa\"\n",
+    )
+    .unwrap();
+
+    assert!(install_plugins(&config).has_failures());
+    write_lockfile(&config).unwrap();
+    let lock = LockFile::load(&path).unwrap();
+    assert_eq!(
+        lock.get(&config.plugins()[0]).unwrap().commit(),
+        remote.sha("main")
+    );
+    assert_eq!(lock.get(&config.plugins()[1]).unwrap().commit(), locked);
+
+    // a lockfile that doesn't parse is left alone
+    std::fs::write(&path, "plugins = 1\n").unwrap();
+    assert!(write_lockfile(&config).is_err());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "plugins = 1\n");
 }